@@ -0,0 +1,159 @@
+#[macro_use]
+extern crate bencher;
+#[macro_use]
+extern crate legv8_asm;
+
+use bencher::Bencher;
+
+use legv8_asm::bit;
+use legv8_asm::bit::Bit;
+use legv8_asm::generator;
+use legv8_asm::instruction::Instruction;
+
+/// Assemble a program a few thousand lines long by repeating a loop
+fn program() -> Vec<Instruction> {
+    let source: String = (0..500).map(|i| format!("\
+loop{0}:
+    LDUR X1, [X2, #0]
+    ADDI X1, X1, 0xFF
+    STUR X1, [X2, #8]
+    MOVZ X3, 0xBEEF, LSL 16
+    SUBIS XZR, X1, 50
+    B.LT loop{0}
+    CBNZ X1, loop{0}
+", i)).collect();
+
+    legv8_asm::assemble(&source).unwrap()
+}
+
+/// The encoder from before `Instruction::encode`, building the bit array
+/// one field at a time, for the instructions `program` uses
+fn encode_bit_array(instruction: &Instruction) -> [Bit; 32] {
+    let mut result: [Bit; 32] = [Bit::Zero; 32];
+
+    match *instruction {
+        Instruction::AddImmediate { n, m: i, destination: d } => {
+            result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
+            result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
+            result[10..22].copy_from_slice(&<[Bit; 12]>::from(i));
+            result[22..32].copy_from_slice(&bit_array![0, 0, 1, 0, 0, 0, 1, 0, 0, 1]);
+        }
+        Instruction::SubtractImmediateSetFlags { n, m: i, destination: d } => {
+            result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
+            result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
+            result[10..22].copy_from_slice(&<[Bit; 12]>::from(i));
+            result[22..32].copy_from_slice(&bit_array![0, 0, 1, 0, 0, 0, 1, 1, 1, 1]);
+        }
+        Instruction::Store { address: a, offset: o, data: d } => {
+            result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
+            result[5..10].copy_from_slice(&<[Bit; 5]>::from(a));
+            result[12..21].copy_from_slice(&<[Bit; 9]>::from(o));
+            result[21..32].copy_from_slice(&bit_array![0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1]);
+        }
+        Instruction::Load { address: a, offset: o, data: d } => {
+            result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
+            result[5..10].copy_from_slice(&<[Bit; 5]>::from(a));
+            result[12..21].copy_from_slice(&<[Bit; 9]>::from(o));
+            result[21..32].copy_from_slice(&bit_array![0, 1, 0, 0, 0, 0, 1, 1, 1, 1, 1]);
+        }
+        Instruction::MoveZero { immediate: i, shift: s, destination: d } => {
+            result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
+            result[5..21].copy_from_slice(&<[Bit; 16]>::from(i));
+            result[21..23].copy_from_slice(&<[Bit; 2]>::from(s));
+            result[23..32].copy_from_slice(&bit_array![1, 0, 1, 0, 0, 1, 0, 1, 1]);
+        }
+        Instruction::CompareBranchNotZero { address: a, r } => {
+            result[0..5].copy_from_slice(&<[Bit; 5]>::from(r));
+            result[5..24].copy_from_slice(&<[Bit; 19]>::from(a));
+            result[24..32].copy_from_slice(&bit_array![1, 0, 1, 0, 1, 1, 0, 1]);
+        }
+        Instruction::ConditionalBranch { address: a, condition: c } => {
+            result[0..5].copy_from_slice(&<[Bit; 5]>::from(c));
+            result[5..24].copy_from_slice(&<[Bit; 19]>::from(a));
+            result[24..32].copy_from_slice(&bit_array![0, 0, 1, 0, 1, 0, 1, 0]);
+        }
+        ref instruction => panic!("`{}` is not in the benchmark program", instruction),
+    }
+
+    result
+}
+
+/// The case ROM generator from before `generate_case_rom_words`, building
+/// each line from the bit array one character at a time
+fn generate_case_rom_bit_array(instructions: Vec<[Bit; 32]>) -> String {
+    let mut rom: String = "".to_string();
+
+    rom.push_str("\
+module rom_case(out, address);
+    output reg [31:0] out;
+    input [15:0] address;
+    always @ (address) begin
+        case (address)
+");
+
+    let cases: String = instructions
+        .iter()
+        .enumerate()
+        .fold("".to_string(), |mut output, (i, instr)| {
+            output.push_str(&format!("            16'd{}: out = 32'b", i));
+
+            let line: String = instr
+                .iter()
+                .rev()
+                .fold("".to_string(), |mut line, bit| {
+                    line.push(match *bit {
+                        Bit::One => '1',
+                        Bit::Zero => '0',
+                    });
+                    line
+                });
+            output.push_str(&line);
+            output.push_str(";\n");
+            output
+        });
+
+    rom.push_str(&cases);
+
+    rom.push_str(
+"            default: out = 32'hD60003E0; // BR XZR
+        endcase
+    end
+end\n"
+    );
+
+    rom
+}
+
+fn case_rom_from_bits(bench: &mut Bencher) {
+    let instructions = program();
+    bench.iter(|| {
+        generate_case_rom_bit_array(instructions.iter().map(encode_bit_array).collect())
+    });
+}
+
+fn case_rom_from_words(bench: &mut Bencher) {
+    let instructions = program();
+    bench.iter(|| {
+        generator::generate_case_rom_words(&instructions.iter().map(Instruction::encode).collect::<Vec<u32>>())
+    });
+}
+
+fn encode_bits(bench: &mut Bencher) {
+    let instructions = program();
+    for instruction in &instructions {
+        assert_eq!(bit::word_from_bits(&encode_bit_array(instruction)), instruction.encode());
+    }
+    bench.iter(|| {
+        instructions.iter().map(encode_bit_array).collect::<Vec<[Bit; 32]>>()
+    });
+}
+
+fn encode_words(bench: &mut Bencher) {
+    let instructions = program();
+    bench.iter(|| {
+        instructions.iter().map(Instruction::encode).collect::<Vec<u32>>()
+    });
+}
+
+benchmark_group!(benches, case_rom_from_bits, case_rom_from_words, encode_bits, encode_words);
+benchmark_main!(benches);
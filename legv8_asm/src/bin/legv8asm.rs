@@ -0,0 +1,587 @@
+extern crate legv8_asm;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::process;
+
+use legv8_asm::diagnostic::Diagnostic;
+use legv8_asm::generator;
+use legv8_asm::generator::{
+    Addressing, Endianness, InitOptions, MemoryOptions, Radix, RawOptions, RecordOptions, RomOptions, VhdlStyle
+};
+
+const USAGE: &str = "\
+Usage: legv8asm [options] [input]
+
+Assemble a LEGv8 program read from `input`, or from stdin if `input` is
+missing or `-`.
+
+Options:
+    -o, --output FILE      Write to FILE instead of stdout
+    -d, --data-output FILE Write the `.data` section to FILE, in the same format
+    -f, --format FORMAT    Output format, one of:
+                               case             verilog case statement ROM (default)
+                               binary           one line of binary digits per instruction
+                               readmemh         hexadecimal memory file for $readmemh
+                               readmemb         binary memory file for $readmemb
+                               readmemh-module  verilog ROM loading a $readmemh file
+                               readmemb-module  verilog ROM loading a $readmemb file
+                               ihex             Intel HEX
+                               srec             Motorola S-record
+                               raw              flat binary, 4 bytes per instruction
+                               coe              Xilinx block RAM COE file
+                               mif              Intel (Altera) MIF file
+                               vhdl             VHDL ROM entity with a constant array
+                               vhdl-case        VHDL ROM entity with a case statement
+        --addresses        Start each line of a memory file with an @address record
+        --disassembly      End each line of a memory file with its instruction
+        --comments         End each line of a memory file with the comment from its source line
+        --memory-file FILE The memory file a ROM module loads, `program.mem` by default
+        --data-memory-file FILE
+                           The memory file a data ROM module loads, `data.mem` by default
+        --base-address N   The address of the first byte in a HEX or S-record file
+        --record-length N  The most data bytes in each HEX or S-record record, 16 by default
+        --big-endian       Put the most significant byte of each word first
+        --size N           Pad a raw binary file up to N bytes
+        --fill N           The byte to pad a raw binary file with, 0 by default
+        --depth N          The number of entries in a COE or MIF file
+        --width N          The bits in each COE or MIF entry, 32 by default
+        --radix RADIX      `hex` (default) or `bin` entries in a COE or MIF file
+        --module-name NAME The name of a generated ROM, `rom_case` by default
+        --data-module-name NAME
+                           The name of a generated data ROM, `data_rom` by default
+        --data-port NAME   The ROM's 32 bit output port, `out` by default
+        --address-port NAME
+                           The ROM's address port, `address` by default
+        --clock-port NAME  The ROM's clock port, `clk` by default
+        --address-width N  The bits in the ROM's address port, 16 by default
+        --byte-addressing  Count the ROM's address in bytes instead of words
+        --default-word N   The ROM's output past the end of the program, BR XZR by default
+        --clocked          Update the ROM's output on the rising clock edge
+        --rom-radix RADIX  `bin` (default) or `hex` words in a ROM
+    -h, --help             Show this message
+";
+
+/// The kinds of output the assembler can write
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Format {
+    CaseRom,
+    Binary,
+    Memory(Radix),
+    MemoryModule(Radix),
+    IntelHex,
+    SRecord,
+    Raw,
+    Coe,
+    Mif,
+    Vhdl(VhdlStyle),
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "case" => Some(Format::CaseRom),
+            "binary" => Some(Format::Binary),
+            "readmemh" => Some(Format::Memory(Radix::Hexadecimal)),
+            "readmemb" => Some(Format::Memory(Radix::Binary)),
+            "readmemh-module" => Some(Format::MemoryModule(Radix::Hexadecimal)),
+            "readmemb-module" => Some(Format::MemoryModule(Radix::Binary)),
+            "ihex" => Some(Format::IntelHex),
+            "srec" => Some(Format::SRecord),
+            "raw" => Some(Format::Raw),
+            "coe" => Some(Format::Coe),
+            "mif" => Some(Format::Mif),
+            "vhdl" => Some(Format::Vhdl(VhdlStyle::Array)),
+            "vhdl-case" => Some(Format::Vhdl(VhdlStyle::Case)),
+            _ => None,
+        }
+    }
+
+    /// Generate the output bytes for a memory holding the words, or a message
+    /// saying why they could not be
+    fn generate(self, words: &[u32], memory: &Memory, options: &Options) -> Result<Vec<u8>, String> {
+        let text = match self {
+            Format::CaseRom => generator::generate_rom(words, &memory.rom)?,
+            Format::Binary => generator::generate_binary_str_words(words),
+            Format::Memory(radix) => generator::generate_commented_memory(
+                words,
+                if options.comments { memory.comments } else { &[] },
+                &MemoryOptions { radix, addresses: options.addresses, disassembly: options.disassembly },
+            ),
+            Format::MemoryModule(radix) => generator::generate_memory_module(
+                memory.file, words.len(), radix, &memory.rom
+            )?,
+            Format::IntelHex => generator::generate_intel_hex(words, &options.records),
+            Format::SRecord => generator::generate_s_record(words, &options.records),
+            Format::Raw => return generator::generate_raw(words, &RawOptions {
+                endianness: options.records.endianness,
+                size: options.size,
+                fill: options.fill,
+            }),
+            Format::Coe => generator::generate_coe(words, &options.init)?,
+            Format::Mif => generator::generate_mif(words, &options.init)?,
+            Format::Vhdl(style) => generator::generate_vhdl_rom(words, style, &memory.rom)?,
+        };
+
+        Ok(text.into_bytes())
+    }
+}
+
+/// The names used for the ROM of one section
+struct Memory<'a> {
+    rom: RomOptions,
+    /// The memory file a ROM module loads
+    file: &'a str,
+    /// The source comment of each word
+    comments: &'a [Option<String>],
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    /// The file to read, or `None` for stdin
+    input: Option<String>,
+    /// The file to write, or `None` for stdout
+    output: Option<String>,
+    /// The file to write the data section to, if any
+    data_output: Option<String>,
+    format: Format,
+    /// Put `@address` records in memory files
+    addresses: bool,
+    /// Put the disassembly of each word in memory files
+    disassembly: bool,
+    /// Put the source comment of each word in memory files
+    comments: bool,
+    /// The memory file loaded by a generated ROM module
+    memory_file: String,
+    /// The memory file loaded by a generated data ROM module
+    data_memory_file: String,
+    /// The layout of Intel HEX and S-record files
+    records: RecordOptions,
+    /// The size to pad raw binary files to
+    size: Option<usize>,
+    /// The byte to pad raw binary files with
+    fill: u8,
+    /// The shape of the block RAM for COE and MIF files
+    init: InitOptions,
+    /// The shape of generated ROMs
+    rom: RomOptions,
+    /// The module name of a generated data ROM
+    data_module_name: String,
+    help: bool,
+}
+
+/// Read a decimal, or hexadecimal with `0x`, number argument
+fn parse_number(number: &str) -> Result<u32, String> {
+    let parsed = match number.strip_prefix("0x").or_else(|| number.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => number.parse(),
+    };
+
+    parsed.map_err(|_| format!("invalid number `{}`", number))
+}
+
+/// Read a `hex` or `bin` radix argument
+fn parse_radix(name: &str) -> Result<Radix, String> {
+    match name {
+        "hex" => Ok(Radix::Hexadecimal),
+        "bin" => Ok(Radix::Binary),
+        _ => Err(format!("unknown radix `{}`", name)),
+    }
+}
+
+/// Read the options from the command line arguments, not including the program name
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options {
+        input: None,
+        output: None,
+        data_output: None,
+        format: Format::CaseRom,
+        addresses: false,
+        disassembly: false,
+        comments: false,
+        memory_file: "program.mem".to_owned(),
+        data_memory_file: "data.mem".to_owned(),
+        records: RecordOptions::default(),
+        size: None,
+        fill: 0,
+        init: InitOptions::default(),
+        rom: RomOptions::default(),
+        data_module_name: "data_rom".to_owned(),
+        help: false,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => options.help = true,
+            "-o" | "--output" => {
+                options.output = Some(args.next().ok_or(format!("`{}` needs a file name", arg))?);
+            }
+            "-d" | "--data-output" => {
+                options.data_output = Some(args.next().ok_or(format!("`{}` needs a file name", arg))?);
+            }
+            "-f" | "--format" => {
+                let name = args.next().ok_or(format!("`{}` needs a format", arg))?;
+                options.format = Format::from_name(&name).ok_or(format!("unknown format `{}`", name))?;
+            }
+            "--addresses" => options.addresses = true,
+            "--disassembly" => options.disassembly = true,
+            "--comments" => options.comments = true,
+            "--memory-file" => {
+                options.memory_file = args.next().ok_or(format!("`{}` needs a file name", arg))?;
+            }
+            "--data-memory-file" => {
+                options.data_memory_file = args.next().ok_or(format!("`{}` needs a file name", arg))?;
+            }
+            "--base-address" => {
+                let number = args.next().ok_or(format!("`{}` needs an address", arg))?;
+                options.records.base_address = parse_number(&number)?;
+            }
+            "--record-length" => {
+                let number = args.next().ok_or(format!("`{}` needs a length", arg))?;
+                options.records.record_length = parse_number(&number)? as usize;
+            }
+            "--big-endian" => options.records.endianness = Endianness::Big,
+            "--size" => {
+                let number = args.next().ok_or(format!("`{}` needs a size", arg))?;
+                options.size = Some(parse_number(&number)? as usize);
+            }
+            "--fill" => {
+                let number = args.next().ok_or(format!("`{}` needs a byte", arg))?;
+                let fill = parse_number(&number)?;
+                if fill > 0xFF {
+                    return Err(format!("fill `{}` does not fit in a byte", number));
+                }
+                options.fill = fill as u8;
+            }
+            "--depth" => {
+                let number = args.next().ok_or(format!("`{}` needs a depth", arg))?;
+                options.init.depth = Some(parse_number(&number)? as usize);
+            }
+            "--width" => {
+                let number = args.next().ok_or(format!("`{}` needs a width", arg))?;
+                options.init.width = parse_number(&number)?;
+            }
+            "--radix" => {
+                let name = args.next().ok_or(format!("`{}` needs a radix", arg))?;
+                options.init.radix = parse_radix(&name)?;
+            }
+            "--module-name" => {
+                options.rom.module_name = args.next().ok_or(format!("`{}` needs a name", arg))?;
+            }
+            "--data-module-name" => {
+                options.data_module_name = args.next().ok_or(format!("`{}` needs a name", arg))?;
+            }
+            "--data-port" => {
+                options.rom.data_port = args.next().ok_or(format!("`{}` needs a name", arg))?;
+            }
+            "--address-port" => {
+                options.rom.address_port = args.next().ok_or(format!("`{}` needs a name", arg))?;
+            }
+            "--clock-port" => {
+                options.rom.clock_port = args.next().ok_or(format!("`{}` needs a name", arg))?;
+            }
+            "--address-width" => {
+                let number = args.next().ok_or(format!("`{}` needs a width", arg))?;
+                options.rom.address_width = parse_number(&number)?;
+            }
+            "--byte-addressing" => options.rom.addressing = Addressing::Byte,
+            "--default-word" => {
+                let number = args.next().ok_or(format!("`{}` needs a word", arg))?;
+                options.rom.default_word = parse_number(&number)?;
+            }
+            "--clocked" => options.rom.clocked = true,
+            "--rom-radix" => {
+                let name = args.next().ok_or(format!("`{}` needs a radix", arg))?;
+                options.rom.radix = parse_radix(&name)?;
+            }
+            "-" => options.input = None,
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+            _ if options.input.is_some() => return Err(format!("unexpected argument `{}`", arg)),
+            _ => options.input = Some(arg),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Format a diagnostic as `file:line:column: severity: message`, followed by
+/// the line it is about with the offending text underlined
+fn report(file: &str, source: &str, diagnostic: &Diagnostic) -> String {
+    let mut report = format!("{}:{}\n", file, diagnostic);
+
+    if let Some(line) = source.lines().nth(diagnostic.line - 1) {
+        // Keep the tabs, so the underline lines up however wide they are shown
+        let indent: String = line.chars()
+            .take(diagnostic.columns.start - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underline = "^".repeat(diagnostic.columns.len().max(1));
+        report.push_str(&format!("    {}\n    {}{}\n", line, indent, underline));
+    }
+
+    report
+}
+
+fn read_input(input: &Option<String>) -> io::Result<String> {
+    let mut source = String::new();
+
+    match *input {
+        Some(ref path) => fs::File::open(path)?.read_to_string(&mut source)?,
+        None => io::stdin().read_to_string(&mut source)?,
+    };
+
+    Ok(source)
+}
+
+fn write_output(output: &Option<String>, bytes: &[u8]) -> io::Result<()> {
+    match *output {
+        Some(ref path) => fs::File::create(path)?.write_all(bytes),
+        None => io::stdout().write_all(bytes),
+    }
+}
+
+/// Assemble with the given options, returning the exit status
+fn run(options: &Options) -> i32 {
+    let file = options.input.as_ref().map_or("<stdin>", |path| path.as_str());
+
+    let source = match read_input(&options.input) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("legv8asm: could not read {}: {}", file, error);
+            return 2;
+        }
+    };
+
+    let image = match legv8_asm::assemble_image(&source) {
+        Ok(image) => image,
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                eprint!("{}", report(file, &source, diagnostic));
+            }
+            return 1;
+        }
+    };
+
+    let text = Memory { rom: options.rom.clone(), file: &options.memory_file, comments: &image.text_comments };
+    if let Err(status) = write_memory(&image.text, &text, &options.output, options) {
+        return status;
+    }
+
+    match options.data_output {
+        Some(_) => {
+            let data = Memory {
+                rom: RomOptions { module_name: options.data_module_name.clone(), ..options.rom.clone() },
+                file: &options.data_memory_file,
+                comments: &image.data_comments,
+            };
+            if let Err(status) = write_memory(&image.data, &data, &options.data_output, options) {
+                return status;
+            }
+        }
+        None if !image.data.is_empty() => {
+            eprintln!("legv8asm: warning: the `.data` section is not written anywhere, use `--data-output`");
+        }
+        None => {}
+    }
+
+    0
+}
+
+/// Generate the output for a memory and write it, returning the exit status if it fails
+fn write_memory(words: &[u32], memory: &Memory, output: &Option<String>, options: &Options) -> Result<(), i32> {
+    let bytes = match options.format.generate(words, memory, options) {
+        Ok(bytes) => bytes,
+        Err(message) => {
+            eprintln!("legv8asm: {}", message);
+            return Err(1);
+        }
+    };
+
+    write_output(output, &bytes).map_err(|error| {
+        eprintln!("legv8asm: could not write output: {}", error);
+        2
+    })
+}
+
+fn main() {
+    let status = match parse_args(env::args().skip(1)) {
+        Ok(ref options) if options.help => {
+            print!("{}", USAGE);
+            0
+        }
+        Ok(ref options) => run(options),
+        Err(message) => {
+            eprint!("legv8asm: {}\n\n{}", message, USAGE);
+            2
+        }
+    };
+
+    process::exit(status);
+}
+
+#[cfg(test)]
+fn args(args: &[&str]) -> Result<Options, String> {
+    parse_args(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn test_parse_args_defaults() {
+    assert_eq!(
+        args(&[]),
+        Ok(Options {
+            input: None,
+            output: None,
+            data_output: None,
+            format: Format::CaseRom,
+            addresses: false,
+            disassembly: false,
+            comments: false,
+            memory_file: "program.mem".to_owned(),
+            data_memory_file: "data.mem".to_owned(),
+            records: RecordOptions::default(),
+            size: None,
+            fill: 0,
+            init: InitOptions::default(),
+            rom: RomOptions::default(),
+            data_module_name: "data_rom".to_owned(),
+            help: false,
+        })
+    );
+}
+
+#[test]
+fn test_parse_args() {
+    assert_eq!(
+        args(&["-f", "binary", "prog.asm", "--output", "prog.txt"]),
+        Ok(Options {
+            input: Some("prog.asm".to_owned()),
+            output: Some("prog.txt".to_owned()),
+            data_output: None,
+            format: Format::Binary,
+            addresses: false,
+            disassembly: false,
+            comments: false,
+            memory_file: "program.mem".to_owned(),
+            data_memory_file: "data.mem".to_owned(),
+            records: RecordOptions::default(),
+            size: None,
+            fill: 0,
+            init: InitOptions::default(),
+            rom: RomOptions::default(),
+            data_module_name: "data_rom".to_owned(),
+            help: false,
+        })
+    );
+}
+
+#[test]
+fn test_parse_args_rom() {
+    let options = args(&[
+        "--module-name", "imem", "--data-module-name", "dmem", "--data-port", "instruction",
+        "--address-port", "pc", "--clock-port", "clock", "--address-width", "8", "--byte-addressing",
+        "--default-word", "0x8B1F03FF", "--clocked", "--rom-radix", "hex",
+    ]).unwrap();
+
+    assert_eq!(
+        options.rom,
+        RomOptions {
+            module_name: "imem".to_owned(),
+            data_port: "instruction".to_owned(),
+            address_port: "pc".to_owned(),
+            clock_port: "clock".to_owned(),
+            address_width: 8,
+            addressing: Addressing::Byte,
+            default_word: 0x8B1F03FF,
+            clocked: true,
+            radix: Radix::Hexadecimal,
+        }
+    );
+    assert_eq!(options.data_module_name, "dmem");
+    assert_eq!(args(&["--rom-radix", "oct"]), Err("unknown radix `oct`".to_owned()));
+}
+
+#[test]
+fn test_parse_args_unknown_format() {
+    assert_eq!(args(&["--format", "elf"]), Err("unknown format `elf`".to_owned()));
+}
+
+#[test]
+fn test_parse_args_missing_output() {
+    assert_eq!(args(&["in.asm", "-o"]), Err("`-o` needs a file name".to_owned()));
+}
+
+#[test]
+fn test_parse_args_memory() {
+    let options = args(&["-f", "readmemb", "--disassembly", "--comments", "--addresses"]).unwrap();
+    assert_eq!(options.format, Format::Memory(Radix::Binary));
+    assert!(options.addresses);
+    assert!(options.disassembly);
+    assert!(options.comments);
+}
+
+#[test]
+fn test_parse_args_records() {
+    let options = args(&["-f", "ihex", "--base-address", "0x8000", "--record-length", "32", "--big-endian"]).unwrap();
+    assert_eq!(options.format, Format::IntelHex);
+    assert_eq!(
+        options.records,
+        RecordOptions { base_address: 0x8000, record_length: 32, endianness: Endianness::Big }
+    );
+}
+
+#[test]
+fn test_parse_args_invalid_number() {
+    assert_eq!(args(&["--base-address", "0xZZ"]), Err("invalid number `0xZZ`".to_owned()));
+}
+
+#[test]
+fn test_parse_args_raw() {
+    let options = args(&["-f", "raw", "--size", "1024", "--fill", "0xFF"]).unwrap();
+    assert_eq!(options.format, Format::Raw);
+    assert_eq!(options.size, Some(1024));
+    assert_eq!(options.fill, 0xFF);
+}
+
+#[test]
+fn test_parse_args_fill_too_large() {
+    assert_eq!(args(&["--fill", "256"]), Err("fill `256` does not fit in a byte".to_owned()));
+}
+
+#[test]
+fn test_parse_args_init() {
+    let options = args(&["-f", "mif", "--depth", "256", "--width", "8", "--radix", "bin"]).unwrap();
+    assert_eq!(options.format, Format::Mif);
+    assert_eq!(options.init, InitOptions { depth: Some(256), width: 8, radix: Radix::Binary });
+}
+
+#[test]
+fn test_parse_args_data_output() {
+    let options = args(&["-d", "data.hex", "--data-memory-file", "ram.hex"]).unwrap();
+    assert_eq!(options.data_output, Some("data.hex".to_owned()));
+    assert_eq!(options.data_memory_file, "ram.hex");
+}
+
+#[test]
+fn test_report() {
+    let source = "ADD X1, X2, X3\n    ADDX X1, X2, X3\n";
+    assert_eq!(
+        report("prog.asm", source, &Diagnostic::error(2, 5, "ADDX", "unknown mnemonic `ADDX`".to_owned())),
+        "\
+prog.asm:2:5: error: unknown mnemonic `ADDX`
+        ADDX X1, X2, X3
+        ^^^^
+"
+    );
+}
+
+#[test]
+fn test_report_tab_indent() {
+    let diagnostic = Diagnostic::error(2, 4, "ADDX", "unknown mnemonic `ADDX`".to_owned());
+
+    assert_eq!(
+        report("program.s", "start:\n\t\t ADDX X1", &diagnostic),
+        "program.s:2:4: error: unknown mnemonic `ADDX`\n    \t\t ADDX X1\n    \t\t ^^^^\n"
+    );
+}
@@ -1,3 +1,5 @@
+// The tests compare against `true` and `false` to read like the conversions they check
+#![cfg_attr(test, allow(clippy::bool_assert_comparison))]
 
 /// A single bit
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -23,6 +25,20 @@ impl Bit {
     }
 }
 
+/// Pack 32 bits, least significant bit first, into a word
+pub fn word_from_bits(bits: &[Bit; 32]) -> u32 {
+    bits.iter().rev().fold(0, |word, &bit| (word << 1) | u32::from(u8::from(bit)))
+}
+
+/// Unpack a word into its 32 bits, least significant bit first
+pub fn bits_from_word(word: u32) -> [Bit; 32] {
+    let mut result = [Bit::Zero; 32];
+    for (i, bit) in result.iter_mut().enumerate() {
+        *bit = Bit::from_bool((word >> i) & 1 == 1);
+    }
+    result
+}
+
 #[macro_export]
 macro_rules! bit_array {
     ($($x:expr),*) => {
@@ -64,6 +80,26 @@ fn test_bits() {
     assert_eq!(bit_array![1, 0, 1, 1], [Bit::One, Bit::Zero, Bit::One, Bit::One]);
 }
 
+#[test]
+fn test_word_from_bits() {
+    assert_eq!(
+        word_from_bits(&bit_array![
+            1, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1
+        ]),
+        0x8000_000D
+    );
+}
+
+#[test]
+fn test_bits_from_word() {
+    assert_eq!(
+        bits_from_word(0x8000_000D),
+        bit_array![
+            1, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1
+        ]
+    );
+}
+
 #[test]
 fn test_bool_bit_true_from() {
     assert_eq!(Bit::One, Bit::from_bool(true));
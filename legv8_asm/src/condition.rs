@@ -1,6 +1,8 @@
+use std::fmt;
+
 use bit::Bit;
 
-#[derive(Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Condition {
     Equal = 0,
     NotEqual,
@@ -20,14 +22,68 @@ pub enum Condition {
     Reserved,
 }
 
+impl Condition {
+    /// Get the condition with the given number
+    pub fn from_u8(number: u8) -> Option<Condition> {
+        match number {
+            0 => Some(Condition::Equal),
+            1 => Some(Condition::NotEqual),
+            2 => Some(Condition::HigherSame),
+            3 => Some(Condition::Lower),
+            4 => Some(Condition::Minus),
+            5 => Some(Condition::PositiveZero),
+            6 => Some(Condition::SignedOverflow),
+            7 => Some(Condition::NoSignedOverflow),
+            8 => Some(Condition::Higher),
+            9 => Some(Condition::LowerSame),
+            10 => Some(Condition::GreaterThanEqual),
+            11 => Some(Condition::LessThan),
+            12 => Some(Condition::GreaterThan),
+            13 => Some(Condition::LessThanEqual),
+            14 => Some(Condition::Always),
+            15 => Some(Condition::Reserved),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Condition::Equal => "EQ",
+            Condition::NotEqual => "NE",
+            Condition::HigherSame => "HS",
+            Condition::Lower => "LO",
+            Condition::Minus => "MI",
+            Condition::PositiveZero => "PL",
+            Condition::SignedOverflow => "VS",
+            Condition::NoSignedOverflow => "VC",
+            Condition::Higher => "HI",
+            Condition::LowerSame => "LS",
+            Condition::GreaterThanEqual => "GE",
+            Condition::LessThan => "LT",
+            Condition::GreaterThan => "GT",
+            Condition::LessThanEqual => "LE",
+            Condition::Always => "AL",
+            Condition::Reserved => "NV",
+        })
+    }
+}
+
+impl From<Condition> for u32 {
+    fn from(condition: Condition) -> u32 {
+        condition as u32
+    }
+}
+
 impl From<Condition> for [Bit; 5] {
     fn from(condition: Condition) -> [Bit; 5] {
         let condition_number = condition as usize as u8;
 
         let mut result = [Bit::Zero; 5];
 
-        for i in 0..5 {
-            result[i] = Bit::from_u8((condition_number >> i as u8) & 1_u8).unwrap();
+        for (i, bit) in result.iter_mut().enumerate() {
+            *bit = Bit::from_u8((condition_number >> i as u8) & 1_u8).unwrap();
         }
 
         result
@@ -44,3 +100,13 @@ fn test_condition_hi() {
     assert_eq!(<[Bit; 5]>::from(Condition::Higher), [Bit::Zero, Bit::Zero, Bit::Zero, Bit::One, Bit::Zero]);
 }
 
+#[test]
+fn test_condition_from_u8() {
+    assert_eq!(Condition::from_u8(8), Some(Condition::Higher));
+    assert_eq!(Condition::from_u8(16), None);
+}
+
+#[test]
+fn test_condition_display() {
+    assert_eq!(Condition::NotEqual.to_string(), "NE");
+}
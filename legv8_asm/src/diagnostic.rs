@@ -0,0 +1,73 @@
+use std::fmt;
+use std::ops::Range;
+
+/// How serious a problem is
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found while assembling a program
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// The line the problem is on, counting from 1
+    pub line: usize,
+    /// The columns of the offending text, counting from 1
+    pub columns: Range<usize>,
+    pub severity: Severity,
+    pub message: String,
+    /// The offending text
+    pub text: String,
+}
+
+impl Diagnostic {
+    /// An error about `text`, found at `column` of `line`
+    pub fn error(line: usize, column: usize, text: &str, message: String) -> Diagnostic {
+        Diagnostic {
+            line,
+            columns: column..column + text.chars().count(),
+            severity: Severity::Error,
+            message,
+            text: text.to_owned(),
+        }
+    }
+}
+
+/// Format the diagnostic as `line:column: severity: message`
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}: {}", self.line, self.columns.start, self.severity, self.message)
+    }
+}
+
+#[test]
+fn test_diagnostic_error() {
+    assert_eq!(
+        Diagnostic::error(3, 1, "ADDX", "unknown mnemonic `ADDX`".to_owned()),
+        Diagnostic {
+            line: 3,
+            columns: 1..5,
+            severity: Severity::Error,
+            message: "unknown mnemonic `ADDX`".to_owned(),
+            text: "ADDX".to_owned(),
+        }
+    );
+}
+
+#[test]
+fn test_diagnostic_display() {
+    assert_eq!(
+        Diagnostic::error(3, 9, "X", "expected register after `,`".to_owned()).to_string(),
+        "3:9: error: expected register after `,`"
+    );
+}
@@ -0,0 +1,368 @@
+use std::fmt;
+
+use bit;
+use bit::Bit;
+use register::Register;
+use register::FloatRegister;
+use shift16::Shift16;
+use condition::Condition;
+use instruction::Instruction;
+use immediate::Immediate6;
+use immediate::Immediate9;
+use immediate::Immediate12;
+use immediate::Immediate16;
+use immediate::Immediate19;
+use immediate::Immediate26;
+
+/// Why a word could not be decoded into an instruction
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    /// No instruction has the opcode in the word
+    UnknownOpcode(u32),
+    /// The word has an instruction's opcode, but sets bits that the
+    /// instruction always leaves zero
+    UnusedBitsSet(u32),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::UnknownOpcode(word) => write!(f, "unknown opcode in 0x{:08X}", word),
+            DecodeError::UnusedBitsSet(word) => write!(f, "unused bits set in 0x{:08X}", word),
+        }
+    }
+}
+
+/// Get `width` bits of `word`, starting at bit `low`
+fn field(word: u32, low: u32, width: u32) -> u32 {
+    (word >> low) & ((1 << width) - 1)
+}
+
+/// Get `width` bits of `word` starting at bit `low`, sign extended
+fn signed_field(word: u32, low: u32, width: u32) -> i32 {
+    ((field(word, low, width) << (32 - width)) as i32) >> (32 - width)
+}
+
+fn register(word: u32, low: u32) -> Register {
+    Register::from_u8(field(word, low, 5) as u8).unwrap()
+}
+
+fn float_register(word: u32, low: u32) -> FloatRegister {
+    FloatRegister::from_u8(field(word, low, 5) as u8).unwrap()
+}
+
+/// Decode a floating point instruction with the R-format opcode for single
+/// or double precision, which the shamt field picks between
+fn decode_float(word: u32, double: bool) -> Option<Instruction> {
+    let d = float_register(word, 0);
+    let n = float_register(word, 5);
+    let m = float_register(word, 16);
+
+    match (field(word, 10, 6), double) {
+        (0b001010, false) => Some(Instruction::FloatAddSingle { n, m, destination: d }),
+        (0b001010, true) => Some(Instruction::FloatAddDouble { n, m, destination: d }),
+        (0b001110, false) => Some(Instruction::FloatSubtractSingle { n, m, destination: d }),
+        (0b001110, true) => Some(Instruction::FloatSubtractDouble { n, m, destination: d }),
+        (0b000010, false) => Some(Instruction::FloatMultiplySingle { n, m, destination: d }),
+        (0b000010, true) => Some(Instruction::FloatMultiplyDouble { n, m, destination: d }),
+        (0b000110, false) => Some(Instruction::FloatDivideSingle { n, m, destination: d }),
+        (0b000110, true) => Some(Instruction::FloatDivideDouble { n, m, destination: d }),
+        (0b001000, false) => Some(Instruction::FloatCompareSingle { n, m }),
+        (0b001000, true) => Some(Instruction::FloatCompareDouble { n, m }),
+        _ => None,
+    }
+}
+
+/// Decode the fields of a word with a known opcode, without checking the unused bits
+fn decode_fields(word: u32) -> Option<Instruction> {
+    let d = register(word, 0);
+    let n = register(word, 5);
+    let m = register(word, 16);
+
+    let alu_immediate = Immediate12(field(word, 10, 12) as i16);
+    let dt_address = Immediate9(signed_field(word, 12, 9) as i16);
+    let shamt = Immediate6(field(word, 10, 6) as i8);
+    let mov_immediate = Immediate16(field(word, 5, 16) as u16 as i16);
+    let mov_shift = Shift16::from_u8(field(word, 21, 2) as u8).unwrap();
+    let branch_address = Immediate26(signed_field(word, 0, 26));
+    let cond_branch_address = Immediate19(signed_field(word, 5, 19));
+
+    // B format opcodes
+    match field(word, 26, 6) {
+        0b000101 => return Some(Instruction::Branch { address: branch_address }),
+        0b100101 => return Some(Instruction::BranchLink { address: branch_address }),
+        _ => {}
+    }
+
+    // CB format opcodes
+    match field(word, 24, 8) {
+        0b10110100 => return Some(Instruction::CompareBranchZero { address: cond_branch_address, r: d }),
+        0b10110101 => return Some(Instruction::CompareBranchNotZero { address: cond_branch_address, r: d }),
+        0b01010100 => return Condition::from_u8(field(word, 0, 5) as u8)
+            .map(|condition| Instruction::ConditionalBranch { address: cond_branch_address, condition }),
+        _ => {}
+    }
+
+    // IW format opcodes
+    match field(word, 23, 9) {
+        0b110100101 => return Some(Instruction::MoveZero { immediate: mov_immediate, shift: mov_shift, destination: d }),
+        0b111100101 => return Some(Instruction::MoveKeep { immediate: mov_immediate, shift: mov_shift, destination: d }),
+        _ => {}
+    }
+
+    // I format opcodes
+    match field(word, 22, 10) {
+        0b1001000100 => return Some(Instruction::AddImmediate { n, m: alu_immediate, destination: d }),
+        0b1101000100 => return Some(Instruction::SubtractImmediate { n, m: alu_immediate, destination: d }),
+        0b1011000100 => return Some(Instruction::AddImmediateSetFlags { n, m: alu_immediate, destination: d }),
+        0b1111000100 => return Some(Instruction::SubtractImmediateSetFlags { n, m: alu_immediate, destination: d }),
+        0b1001001000 => return Some(Instruction::AndImmediate { n, m: alu_immediate, destination: d }),
+        0b1011001000 => return Some(Instruction::OrImmediate { n, m: alu_immediate, destination: d }),
+        0b1101001000 => return Some(Instruction::XorImmediate { n, m: alu_immediate, destination: d }),
+        0b1111001000 => return Some(Instruction::AndImmediateSetFlags { n, m: alu_immediate, destination: d }),
+        _ => {}
+    }
+
+    // R and D format opcodes
+    match field(word, 21, 11) {
+        0b10001011000 => Some(Instruction::Add { n, m, shift: shamt, destination: d }),
+        0b11001011000 => Some(Instruction::Subtract { n, m, shift: shamt, destination: d }),
+        0b10101011000 => Some(Instruction::AddSetFlags { n, m, destination: d }),
+        0b11101011000 => Some(Instruction::SubtractSetFlags { n, m, destination: d }),
+        0b10001010000 => Some(Instruction::And { n, m, shift: shamt, destination: d }),
+        0b10101010000 => Some(Instruction::Or { n, m, shift: shamt, destination: d }),
+        0b11001010000 => Some(Instruction::Xor { n, m, shift: shamt, destination: d }),
+        0b11101010000 => Some(Instruction::AndSetFlags { n, m, destination: d }),
+        0b11010011010 => Some(Instruction::LogicalShiftRight { n, m: shamt, destination: d }),
+        0b11010011011 => Some(Instruction::LogicalShiftLeft { n, m: shamt, destination: d }),
+        0b11010110000 => Some(Instruction::BranchRegister { r: n }),
+        0b10011011000 => Some(Instruction::Multiply { n, m, destination: d }),
+        0b10011011010 => Some(Instruction::SignedMultiplyHigh { n, m, destination: d }),
+        0b10011011110 => Some(Instruction::UnsignedMultiplyHigh { n, m, destination: d }),
+        0b10011010110 if field(word, 10, 6) == 0b000010 => Some(Instruction::SignedDivide { n, m, destination: d }),
+        0b10011010110 => Some(Instruction::UnsignedDivide { n, m, destination: d }),
+        0b11111000000 => Some(Instruction::Store { address: n, offset: dt_address, data: d }),
+        0b11111000010 => Some(Instruction::Load { address: n, offset: dt_address, data: d }),
+        0b00111000000 => Some(Instruction::StoreByte { address: n, offset: dt_address, data: d }),
+        0b00111000010 => Some(Instruction::LoadByte { address: n, offset: dt_address, data: d }),
+        0b10111000000 => Some(Instruction::StoreWord { address: n, offset: dt_address, data: d }),
+        0b10111000100 => Some(Instruction::LoadSignedWord { address: n, offset: dt_address, data: d }),
+        0b01111000000 => Some(Instruction::StoreHalfword { address: n, offset: dt_address, data: d }),
+        0b01111000010 => Some(Instruction::LoadHalfword { address: n, offset: dt_address, data: d }),
+        0b00011110001 => decode_float(word, false),
+        0b00011110011 => decode_float(word, true),
+        0b10111100000 => Some(Instruction::StoreSingle { address: n, offset: dt_address, data: float_register(word, 0) }),
+        0b10111100010 => Some(Instruction::LoadSingle { address: n, offset: dt_address, data: float_register(word, 0) }),
+        0b11111100000 => Some(Instruction::StoreDouble { address: n, offset: dt_address, data: float_register(word, 0) }),
+        0b11111100010 => Some(Instruction::LoadDouble { address: n, offset: dt_address, data: float_register(word, 0) }),
+        0b11001000010 => Some(Instruction::LoadExclusive { address: n, data: d }),
+        0b11001000000 => Some(Instruction::StoreExclusive { address: n, data: d, status: m }),
+        _ => None,
+    }
+}
+
+/// Decode a 32 bit word into the instruction it encodes
+pub fn decode(word: u32) -> Result<Instruction, DecodeError> {
+    let instruction = decode_fields(word).ok_or(DecodeError::UnknownOpcode(word))?;
+
+    // Encoding the instruction again only gives back the same word if all
+    // the bits the instruction does not use were zero
+    if instruction.encode() == word {
+        Ok(instruction)
+    } else {
+        Err(DecodeError::UnusedBitsSet(word))
+    }
+}
+
+/// Decode an instruction from its bits, least significant bit first
+pub fn decode_bits(bits: &[Bit; 32]) -> Result<Instruction, DecodeError> {
+    decode(bit::word_from_bits(bits))
+}
+
+/// Disassemble words into LEGv8 assembly, one instruction per line
+///
+/// Each line ends in a comment with the address and the word it came from.
+/// Words that do not decode are left as just a comment saying why.
+pub fn disassemble(words: &[u32]) -> String {
+    words.iter().enumerate().fold("".to_string(), |mut output, (address, &word)| {
+        match decode(word) {
+            Ok(instruction) => output.push_str(&format!(
+                "{:<24}// {}: 0x{:08X}\n", instruction.to_string(), address, word
+            )),
+            Err(error) => output.push_str(&format!("// {}: {}\n", address, error)),
+        }
+        output
+    })
+}
+
+#[test]
+fn test_decode_add() {
+    assert_eq!(
+        decode(0x8B010043),
+        Ok(Instruction::Add { n: Register::X2, m: Register::X1, shift: Immediate6(0), destination: Register::X3 })
+    );
+}
+
+#[test]
+fn test_decode_add_immediate() {
+    assert_eq!(
+        decode(0x910004E7),
+        Ok(Instruction::AddImmediate { n: Register::X7, m: Immediate12(1), destination: Register::X7 })
+    );
+}
+
+#[test]
+fn test_decode_store() {
+    assert_eq!(
+        decode(0xF80320F7),
+        Ok(Instruction::Store { address: Register::X7, offset: Immediate9(50), data: Register::X23 })
+    );
+}
+
+#[test]
+fn test_decode_negative_load() {
+    assert_eq!(
+        decode(0xF85F8041),
+        Ok(Instruction::Load { address: Register::X2, offset: Immediate9(-8), data: Register::X1 })
+    );
+}
+
+#[test]
+fn test_decode_move_keep() {
+    assert_eq!(
+        decode(0xF2B7DDE2),
+        Ok(Instruction::MoveKeep { immediate: Immediate16(0xBEEF_u16 as i16), shift: Shift16::Shift16, destination: Register::X2 })
+    );
+}
+
+#[test]
+fn test_decode_logical_shift_left() {
+    assert_eq!(
+        decode(0xD3601822),
+        Ok(Instruction::LogicalShiftLeft { n: Register::X1, m: Immediate6(6), destination: Register::X2 })
+    );
+}
+
+#[test]
+fn test_decode_load_signed_word() {
+    assert_eq!(
+        decode(0xB89FC041),
+        Ok(Instruction::LoadSignedWord { address: Register::X2, offset: Immediate9(-4), data: Register::X1 })
+    );
+}
+
+#[test]
+fn test_decode_exclusive() {
+    assert_eq!(decode(0xC85F7C20), Ok(Instruction::LoadExclusive { address: Register::X1, data: Register::X0 }));
+    assert_eq!(
+        decode(0xC80A7D69),
+        Ok(Instruction::StoreExclusive { address: Register::X11, data: Register::X9, status: Register::X10 })
+    );
+    assert_eq!(decode(0xC85F0020), Err(DecodeError::UnusedBitsSet(0xC85F0020)));
+}
+
+#[test]
+fn test_decode_shifted_register() {
+    assert_eq!(
+        decode(0x8B031041),
+        Ok(Instruction::Add { n: Register::X2, m: Register::X3, shift: Immediate6(4), destination: Register::X1 })
+    );
+}
+
+#[test]
+fn test_decode_float() {
+    let (n, m, destination) = (FloatRegister::F2, FloatRegister::F1, FloatRegister::F3);
+    assert_eq!(decode(0x1E612843), Ok(Instruction::FloatAddDouble { n, m, destination }));
+    assert_eq!(decode(0x1E211843), Ok(Instruction::FloatDivideSingle { n, m, destination }));
+    assert_eq!(decode(0x1E212040), Ok(Instruction::FloatCompareSingle { n, m }));
+    assert_eq!(decode(0x1E212043), Err(DecodeError::UnusedBitsSet(0x1E212043)));
+    assert_eq!(decode(0x1E212C43), Err(DecodeError::UnknownOpcode(0x1E212C43)));
+    assert_eq!(
+        decode(0xFC5F8041),
+        Ok(Instruction::LoadDouble { address: Register::X2, offset: Immediate9(-8), data: FloatRegister::F1 })
+    );
+}
+
+#[test]
+fn test_decode_divide() {
+    assert_eq!(
+        decode(0x9AC10843),
+        Ok(Instruction::SignedDivide { n: Register::X2, m: Register::X1, destination: Register::X3 })
+    );
+    assert_eq!(
+        decode(0x9AC10C43),
+        Ok(Instruction::UnsignedDivide { n: Register::X2, m: Register::X1, destination: Register::X3 })
+    );
+    assert_eq!(decode(0x9AC10043), Err(DecodeError::UnusedBitsSet(0x9AC10043)));
+}
+
+#[test]
+fn test_decode_multiply() {
+    assert_eq!(
+        decode(0x9B017C43),
+        Ok(Instruction::Multiply { n: Register::X2, m: Register::X1, destination: Register::X3 })
+    );
+}
+
+#[test]
+fn test_decode_negative_branch() {
+    assert_eq!(
+        decode(0x17FFFFFA),
+        Ok(Instruction::Branch { address: Immediate26(-6) })
+    );
+}
+
+#[test]
+fn test_decode_conditional_branch() {
+    assert_eq!(
+        decode(0x54000181),
+        Ok(Instruction::ConditionalBranch { address: Immediate19(12), condition: Condition::NotEqual })
+    );
+}
+
+#[test]
+fn test_decode_compare_branch_not_zero() {
+    assert_eq!(
+        decode(0xB5FFFFE1),
+        Ok(Instruction::CompareBranchNotZero { address: Immediate19(-1), r: Register::X1 })
+    );
+}
+
+#[test]
+fn test_decode_unknown_opcode() {
+    assert_eq!(decode(0), Err(DecodeError::UnknownOpcode(0)));
+}
+
+#[test]
+fn test_decode_unused_bits_set() {
+    assert_eq!(decode(0xD600001F), Err(DecodeError::UnusedBitsSet(0xD600001F)));
+}
+
+#[test]
+fn test_decode_branch_register() {
+    assert_eq!(decode(0xD60003E0), Ok(Instruction::BranchRegister { r: Register::XZR }));
+}
+
+#[test]
+fn test_decode_bits() {
+    assert_eq!(
+        decode_bits(&bit_array![
+            1, 1, 1, 0, 1, 1, 1, 1, 0, 0, 0, 0, 0, 1, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1
+        ]),
+        Ok(Instruction::Store { address: Register::X7, offset: Immediate9(50), data: Register::X23 })
+    );
+}
+
+#[test]
+fn test_disassemble() {
+    assert_eq!(
+        disassemble(&[0xF80320F7, 0x910004E7, 0xD2A00020, 0x17FFFFFD, 0xFFFFFFFF]),
+"\
+STUR X23, [X7, #50]     // 0: 0xF80320F7
+ADDI X7, X7, 1          // 1: 0x910004E7
+MOVZ X0, 1, LSL 16      // 2: 0xD2A00020
+B -3                    // 3: 0x17FFFFFD
+// 4: unknown opcode in 0xFFFFFFFF
+"
+    );
+}
@@ -0,0 +1,176 @@
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UnaryOperator {
+    /// `-`
+    Negate,
+    /// `~`, flipping every bit
+    Not,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    /// Signed division, rounding towards zero
+    Divide,
+    ShiftLeft,
+    /// Arithmetic shift, keeping the sign
+    ShiftRight,
+    And,
+    Or,
+}
+
+/// A constant expression, like `(end - start) / 8`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression<'a> {
+    Number(i64),
+    /// A label or a constant from `.equ`
+    Symbol(&'a str),
+    Unary(UnaryOperator, Box<Expression<'a>>),
+    Binary(BinaryOperator, Box<Expression<'a>>, Box<Expression<'a>>),
+}
+
+/// Why an expression could not be evaluated
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExpressionError<'a> {
+    /// A label or constant that is not defined
+    Undefined(&'a str),
+    /// A constant whose value depends on itself
+    Recursive(&'a str),
+    DivideByZero,
+    /// A result or shift amount that does not fit in 64 bits
+    Overflow,
+}
+
+impl<'a> fmt::Display for ExpressionError<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExpressionError::Undefined(name) => write!(f, "undefined symbol `{}`", name),
+            ExpressionError::Recursive(name) => write!(f, "constant `{}` is defined in terms of itself", name),
+            ExpressionError::DivideByZero => write!(f, "division by zero"),
+            ExpressionError::Overflow => write!(f, "value does not fit in 64 bits"),
+        }
+    }
+}
+
+impl<'a> Expression<'a> {
+    /// Whether the expression can be evaluated without knowing any symbols
+    pub fn is_constant(&self) -> bool {
+        match *self {
+            Expression::Number(_) => true,
+            Expression::Symbol(_) => false,
+            Expression::Unary(_, ref operand) => operand.is_constant(),
+            Expression::Binary(_, ref left, ref right) => left.is_constant() && right.is_constant(),
+        }
+    }
+
+    /// Work out the value of the expression, getting the value of each symbol from `symbol`
+    pub fn evaluate<F>(&self, symbol: &F) -> Result<i64, ExpressionError<'a>>
+        where F: Fn(&'a str) -> Result<i64, ExpressionError<'a>>
+    {
+        match *self {
+            Expression::Number(number) => Ok(number),
+            Expression::Symbol(name) => symbol(name),
+            Expression::Unary(operator, ref operand) => {
+                let operand = operand.evaluate(symbol)?;
+
+                match operator {
+                    UnaryOperator::Negate => operand.checked_neg().ok_or(ExpressionError::Overflow),
+                    UnaryOperator::Not => Ok(!operand),
+                }
+            }
+            Expression::Binary(operator, ref left, ref right) => {
+                let left = left.evaluate(symbol)?;
+                let right = right.evaluate(symbol)?;
+
+                match operator {
+                    BinaryOperator::Add => left.checked_add(right).ok_or(ExpressionError::Overflow),
+                    BinaryOperator::Subtract => left.checked_sub(right).ok_or(ExpressionError::Overflow),
+                    BinaryOperator::Multiply => left.checked_mul(right).ok_or(ExpressionError::Overflow),
+                    BinaryOperator::Divide if right == 0 => Err(ExpressionError::DivideByZero),
+                    BinaryOperator::Divide => left.checked_div(right).ok_or(ExpressionError::Overflow),
+                    BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight if !(0..64).contains(&right) => {
+                        Err(ExpressionError::Overflow)
+                    }
+                    BinaryOperator::ShiftLeft => Ok(left << right),
+                    BinaryOperator::ShiftRight => Ok(left >> right),
+                    BinaryOperator::And => Ok(left & right),
+                    BinaryOperator::Or => Ok(left | right),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn no_symbols(name: &str) -> Result<i64, ExpressionError<'_>> {
+    Err(ExpressionError::Undefined(name))
+}
+
+#[cfg(test)]
+fn binary<'a>(operator: BinaryOperator, left: Expression<'a>, right: Expression<'a>) -> Expression<'a> {
+    Expression::Binary(operator, Box::new(left), Box::new(right))
+}
+
+#[test]
+fn test_evaluate() {
+    // (3 + 5) * 2 - ~0
+    let expression = binary(
+        BinaryOperator::Subtract,
+        binary(
+            BinaryOperator::Multiply,
+            binary(BinaryOperator::Add, Expression::Number(3), Expression::Number(5)),
+            Expression::Number(2)
+        ),
+        Expression::Unary(UnaryOperator::Not, Box::new(Expression::Number(0)))
+    );
+
+    assert!(expression.is_constant());
+    assert_eq!(expression.evaluate(&no_symbols), Ok(17));
+}
+
+#[test]
+fn test_evaluate_symbols() {
+    let expression = binary(BinaryOperator::Subtract, Expression::Symbol("end"), Expression::Symbol("start"));
+
+    assert!(!expression.is_constant());
+    assert_eq!(
+        expression.evaluate(&|name| match name {
+            "start" => Ok(8),
+            "end" => Ok(40),
+            _ => Err(ExpressionError::Undefined(name)),
+        }),
+        Ok(32)
+    );
+    assert_eq!(expression.evaluate(&no_symbols), Err(ExpressionError::Undefined("end")));
+}
+
+#[test]
+fn test_evaluate_shifts() {
+    assert_eq!(
+        binary(BinaryOperator::ShiftRight, Expression::Number(-16), Expression::Number(2)).evaluate(&no_symbols),
+        Ok(-4)
+    );
+    assert_eq!(
+        binary(BinaryOperator::ShiftLeft, Expression::Number(1), Expression::Number(64)).evaluate(&no_symbols),
+        Err(ExpressionError::Overflow)
+    );
+}
+
+#[test]
+fn test_evaluate_divide_by_zero() {
+    assert_eq!(
+        binary(BinaryOperator::Divide, Expression::Number(1), Expression::Number(0)).evaluate(&no_symbols),
+        Err(ExpressionError::DivideByZero)
+    );
+}
+
+#[test]
+fn test_evaluate_overflow() {
+    assert_eq!(
+        binary(BinaryOperator::Multiply, Expression::Number(i64::MAX), Expression::Number(2)).evaluate(&no_symbols),
+        Err(ExpressionError::Overflow)
+    );
+}
@@ -1,29 +1,28 @@
+use std::cmp;
+use std::fmt::Write;
+
+use bit;
 use bit::Bit;
+use disassembler;
+
+/// Write each word as a line of 32 binary digits, most significant bit first
+pub fn generate_binary_str_words(words: &[u32]) -> String {
+    let mut output = String::with_capacity(words.len() * 33);
+
+    for word in words {
+        writeln!(output, "{:032b}", word).unwrap();
+    }
+
+    output
+}
 
 pub fn generate_binary_str(instructions: Vec<[Bit; 32]>) -> String {
-    instructions
-        .iter()
-        .fold("".to_string(), |mut output, instr| {
-            output.push_str(&instr
-                .iter()
-                .rev()
-                .fold("".to_string(), |mut line, bit| {
-                    line.push({
-                        match bit {
-                            &Bit::One => '1',
-                            &Bit::Zero => '0',
-                        }
-                    });
-                    line
-                }));
-
-            output.push('\n');
-            output
-        })
+    generate_binary_str_words(&instructions.iter().map(bit::word_from_bits).collect::<Vec<u32>>())
 }
 
-pub fn generate_case_rom(instructions: Vec<[Bit; 32]>) -> String {
-    let mut rom: String = "".to_string();
+/// Generate a verilog module that outputs the word at each address with a case statement
+pub fn generate_case_rom_words(words: &[u32]) -> String {
+    let mut rom = String::with_capacity(200 + words.len() * 54);
 
     // Add the header
     rom.push_str("\
@@ -34,48 +33,10 @@ module rom_case(out, address);
         case (address)
 ");
 
-    // Generate the cases
-    let cases: String = instructions
-         // Create an iterator
-        .iter()
-        // Include the index of the instruction from the vector
-        .enumerate()
-        // Build up the cases from the vector of bit arrays, starting with ""
-        .fold("".to_string(), |mut output, (i, instr)| {
-
-            // Add the line header with the address to match
-            output.push_str(&format!("            16'd{}: out = 32'b", i));
-
-            // Generate the binary instruction
-            let line: String = instr
-                .iter() // Create an iterator
-                .rev() // Reverse it so least significant bit is last
-                // Build up the binary instruction from the bit array, starting with ""
-                .fold("".to_string(), |mut line, bit| {
-
-                    // Add the bit to the line
-                    line.push({
-                        match bit {
-                            &Bit::One => '1',
-                            &Bit::Zero => '0',
-                        }
-                    });
-
-                    // Return the line
-                    line
-                });
-            // Add the binary instruction to the jump table
-            output.push_str(&line);
-
-            // Add the semicolon and newline
-            output.push_str(";\n");
-
-            // return the case
-            output
-        });
-
-    // Add the cases to the rom module
-    rom.push_str(&cases);
+    // Add a case for each word, with its address to match
+    for (i, word) in words.iter().enumerate() {
+        writeln!(rom, "            16'd{}: out = 32'b{:032b};", i, word).unwrap();
+    }
 
     // Add the closing bits of the function
     rom.push_str(
@@ -89,6 +50,746 @@ end\n"
     rom
 }
 
+pub fn generate_case_rom(instructions: Vec<[Bit; 32]>) -> String {
+    generate_case_rom_words(&instructions.iter().map(bit::word_from_bits).collect::<Vec<u32>>())
+}
+
+/// How the ROM's address port counts
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Addressing {
+    /// Each address is one 32 bit word
+    Word,
+    /// Each address is one byte, so words are at multiples of 4
+    Byte,
+}
+
+/// How words are written in the generated verilog
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Radix {
+    Binary,
+    Hexadecimal,
+}
+
+/// The shape of a generated verilog ROM module
+#[derive(Clone, Debug, PartialEq)]
+pub struct RomOptions {
+    pub module_name: String,
+    /// The name of the 32 bit output port
+    pub data_port: String,
+    pub address_port: String,
+    /// The name of the clock port, only used when `clocked` is set
+    pub clock_port: String,
+    /// The number of bits in the address port
+    pub address_width: u32,
+    pub addressing: Addressing,
+    /// The word output for addresses past the end of the program
+    pub default_word: u32,
+    /// Register the output on the rising clock edge instead of
+    /// updating it whenever the address changes
+    pub clocked: bool,
+    pub radix: Radix,
+}
+
+/// The names and widths `generate_case_rom_words` uses, though `generate_rom`
+/// writes the default word in binary like the others and ends the module
+/// with `endmodule`
+impl Default for RomOptions {
+    fn default() -> RomOptions {
+        RomOptions {
+            module_name: "rom_case".to_owned(),
+            data_port: "out".to_owned(),
+            address_port: "address".to_owned(),
+            clock_port: "clk".to_owned(),
+            address_width: 16,
+            addressing: Addressing::Word,
+            default_word: 0xD60003E0,
+            clocked: false,
+            radix: Radix::Binary,
+        }
+    }
+}
+
+impl RomOptions {
+    /// How far the address moves for each word
+    fn step(&self) -> usize {
+        match self.addressing {
+            Addressing::Word => 1,
+            Addressing::Byte => 4,
+        }
+    }
+
+    /// Check the address port has at least `minimum` bits, and enough to
+    /// address `words` words
+    fn check_address_width(&self, minimum: u32, words: usize) -> Result<(), String> {
+        if self.address_width < minimum {
+            return Err(format!("address width {} is too narrow, expected at least {}", self.address_width, minimum));
+        }
+
+        let last_address = words.saturating_sub(1) * self.step();
+        if 1_usize.checked_shl(self.address_width).is_some_and(|size| last_address >= size) {
+            return Err(format!(
+                "{} words need address {}, which does not fit in an address width of {}",
+                words, last_address, self.address_width
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Write a word as a 32 bit verilog literal
+    fn literal(&self, word: u32) -> String {
+        match self.radix {
+            Radix::Binary => format!("32'b{:032b}", word),
+            Radix::Hexadecimal => format!("32'h{:08X}", word),
+        }
+    }
+
+    /// Clocked outputs are registers, so they get non blocking assignments
+    fn assign(&self) -> &'static str {
+        if self.clocked { "<=" } else { "=" }
+    }
+
+    /// Write the module line and the port declarations
+    fn write_ports(&self, rom: &mut String) {
+        if self.clocked {
+            writeln!(rom, "module {}({}, {}, {});", self.module_name, self.data_port, self.clock_port, self.address_port).unwrap();
+            writeln!(rom, "    output reg [31:0] {};", self.data_port).unwrap();
+            writeln!(rom, "    input {};", self.clock_port).unwrap();
+        } else {
+            writeln!(rom, "module {}({}, {});", self.module_name, self.data_port, self.address_port).unwrap();
+            writeln!(rom, "    output reg [31:0] {};", self.data_port).unwrap();
+        }
+        writeln!(rom, "    input [{}:0] {};", self.address_width - 1, self.address_port).unwrap();
+    }
+
+    /// Write the start of the block that sets the output
+    fn write_always(&self, rom: &mut String) {
+        if self.clocked {
+            writeln!(rom, "    always @ (posedge {}) begin", self.clock_port).unwrap();
+        } else {
+            writeln!(rom, "    always @ ({}) begin", self.address_port).unwrap();
+        }
+    }
+
+    /// Write the default word, with the instruction it is if there is one
+    fn write_default(&self, rom: &mut String) {
+        write!(rom, "{};", self.literal(self.default_word)).unwrap();
+        match disassembler::decode(self.default_word) {
+            Ok(instruction) => writeln!(rom, " // {}", instruction).unwrap(),
+            Err(_) => rom.push('\n'),
+        }
+    }
+}
+
+/// Generate a verilog ROM module shaped by `options` that outputs the word at
+/// each address with a case statement, or a message saying why it could not be
+pub fn generate_rom(words: &[u32], options: &RomOptions) -> Result<String, String> {
+    options.check_address_width(1, words.len())?;
+
+    let mut rom = String::with_capacity(300 + words.len() * 54);
+
+    let data = &options.data_port;
+    let address = &options.address_port;
+    let assign = options.assign();
+
+    // Add the header
+    options.write_ports(&mut rom);
+    options.write_always(&mut rom);
+    writeln!(rom, "        case ({})", address).unwrap();
+
+    // Add a case for each word, with its address to match
+    for (i, &word) in words.iter().enumerate() {
+        writeln!(
+            rom, "            {}'d{}: {} {} {};",
+            options.address_width, i * options.step(), data, assign, options.literal(word)
+        ).unwrap();
+    }
+
+    // Add the default
+    write!(rom, "            default: {} {} ", data, assign).unwrap();
+    options.write_default(&mut rom);
+
+    rom.push_str("        endcase\n    end\nendmodule\n");
+
+    Ok(rom)
+}
+
+/// What goes in a `$readmemh` or `$readmemb` memory file
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MemoryOptions {
+    /// Hexadecimal for `$readmemh`, binary for `$readmemb`
+    pub radix: Radix,
+    /// Start each line with an `@address` record
+    pub addresses: bool,
+    /// End each line with a comment holding the instruction the word decodes to
+    pub disassembly: bool,
+}
+
+impl Default for MemoryOptions {
+    fn default() -> MemoryOptions {
+        MemoryOptions {
+            radix: Radix::Hexadecimal,
+            addresses: false,
+            disassembly: false,
+        }
+    }
+}
+
+/// Generate a memory file for `$readmemh` or `$readmemb`, one word per line
+pub fn generate_memory(words: &[u32], options: &MemoryOptions) -> String {
+    generate_commented_memory(words, &[], options)
+}
+
+/// Generate a memory file for `$readmemh` or `$readmemb`, one word per line,
+/// ending the line of each word that has a source comment in `comments` with it
+pub fn generate_commented_memory(words: &[u32], comments: &[Option<String>], options: &MemoryOptions) -> String {
+    let mut memory = String::with_capacity(words.len() * 64);
+
+    for (i, &word) in words.iter().enumerate() {
+        if options.addresses {
+            write!(memory, "@{:08X} ", i).unwrap();
+        }
+
+        match options.radix {
+            Radix::Binary => write!(memory, "{:032b}", word).unwrap(),
+            Radix::Hexadecimal => write!(memory, "{:08X}", word).unwrap(),
+        }
+
+        // Words that are not instructions just get no disassembly
+        let disassembly = match disassembler::decode(word) {
+            Ok(instruction) if options.disassembly => Some(instruction.to_string()),
+            _ => None,
+        };
+
+        match (disassembly, comments.get(i).and_then(Option::as_ref)) {
+            (Some(instruction), Some(comment)) => writeln!(memory, " // {}: {}", instruction, comment).unwrap(),
+            (Some(instruction), None) => writeln!(memory, " // {}", instruction).unwrap(),
+            (None, Some(comment)) => writeln!(memory, " // {}", comment).unwrap(),
+            (None, None) => memory.push('\n'),
+        }
+    }
+
+    memory
+}
+
+/// Generate a verilog ROM module shaped by `options` that loads `depth` words
+/// from the memory file `file`, written with `radix`, or a message saying why
+/// it could not be, like a `depth` too deep to address
+pub fn generate_memory_module(file: &str, depth: usize, radix: Radix, options: &RomOptions) -> Result<String, String> {
+    options.check_address_width(1, depth)?;
+
+    let mut rom = String::with_capacity(600);
+
+    let data = &options.data_port;
+    let address = &options.address_port;
+    let assign = options.assign();
+    let depth = depth.max(1);
+
+    options.write_ports(&mut rom);
+
+    // Add the memory and load it from the file
+    let task = match radix {
+        Radix::Binary => "$readmemb",
+        Radix::Hexadecimal => "$readmemh",
+    };
+    writeln!(rom, "    reg [31:0] memory [0:{}];", depth - 1).unwrap();
+    writeln!(rom, "    initial {}(\"{}\", memory);", task, file).unwrap();
+
+    // Read the memory, or the default word past the end of it
+    let (index, end) = match options.addressing {
+        Addressing::Word => (address.to_string(), depth),
+        Addressing::Byte => (format!("{} >> 2", address), depth * 4),
+    };
+    options.write_always(&mut rom);
+    writeln!(rom, "        if ({} < {})", address, end).unwrap();
+    writeln!(rom, "            {} {} memory[{}];", data, assign, index).unwrap();
+    writeln!(rom, "        else").unwrap();
+    write!(rom, "            {} {} ", data, assign).unwrap();
+    options.write_default(&mut rom);
+
+    rom.push_str("    end\nendmodule\n");
+
+    Ok(rom)
+}
+
+/// The order of the bytes in each word
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Endianness {
+    /// Least significant byte first
+    Little,
+    /// Most significant byte first
+    Big,
+}
+
+/// Split words into bytes in the order given by `endianness`
+pub fn word_bytes(words: &[u32], endianness: Endianness) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(words.len() * 4);
+
+    for &word in words {
+        match endianness {
+            Endianness::Little => bytes.extend_from_slice(&word.to_le_bytes()),
+            Endianness::Big => bytes.extend_from_slice(&word.to_be_bytes()),
+        }
+    }
+
+    bytes
+}
+
+/// How words are laid out in Intel HEX and S-record files
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RecordOptions {
+    /// The address of the first byte
+    pub base_address: u32,
+    /// The most data bytes in one record, limited to what the format can hold
+    pub record_length: usize,
+    pub endianness: Endianness,
+}
+
+impl Default for RecordOptions {
+    fn default() -> RecordOptions {
+        RecordOptions {
+            base_address: 0,
+            record_length: 16,
+            endianness: Endianness::Little,
+        }
+    }
+}
+
+/// Write one Intel HEX record, with its checksum
+fn write_intel_hex_record(hex: &mut String, address: u16, kind: u8, data: &[u8]) {
+    write!(hex, ":{:02X}{:04X}{:02X}", data.len(), address, kind).unwrap();
+
+    let mut sum = (data.len() as u8)
+        .wrapping_add((address >> 8) as u8)
+        .wrapping_add(address as u8)
+        .wrapping_add(kind);
+
+    for &byte in data {
+        write!(hex, "{:02X}", byte).unwrap();
+        sum = sum.wrapping_add(byte);
+    }
+
+    // The checksum is the two's complement of the sum of the other bytes
+    writeln!(hex, "{:02X}", sum.wrapping_neg()).unwrap();
+}
+
+/// Generate an Intel HEX file holding the words
+pub fn generate_intel_hex(words: &[u32], options: &RecordOptions) -> String {
+    let bytes = word_bytes(words, options.endianness);
+    let record_length = options.record_length.clamp(1, 255);
+
+    let mut hex = String::with_capacity(bytes.len() * 3);
+    let mut upper_address = 0;
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        let address = options.base_address.wrapping_add(offset as u32);
+
+        // Data records only hold the low 16 bits of the address, so set the
+        // upper 16 bits with an extended linear address record when they change
+        if address >> 16 != upper_address {
+            upper_address = address >> 16;
+            write_intel_hex_record(&mut hex, 0, 0x04, &[(upper_address >> 8) as u8, upper_address as u8]);
+        }
+
+        // Stop records at the end of each 64K block, where the upper bits change
+        let length = cmp::min(
+            cmp::min(record_length, bytes.len() - offset),
+            0x10000 - (address & 0xFFFF) as usize,
+        );
+
+        write_intel_hex_record(&mut hex, address as u16, 0x00, &bytes[offset..offset + length]);
+        offset += length;
+    }
+
+    write_intel_hex_record(&mut hex, 0, 0x01, &[]);
+
+    hex
+}
+
+/// Write one S-record, with its checksum
+fn write_s_record(srec: &mut String, kind: u8, address: u32, address_length: usize, data: &[u8]) {
+    let count = address_length + data.len() + 1;
+    write!(srec, "S{}{:02X}", kind, count).unwrap();
+
+    let mut sum = count as u8;
+
+    for i in (0..address_length).rev() {
+        let byte = (address >> (i * 8)) as u8;
+        write!(srec, "{:02X}", byte).unwrap();
+        sum = sum.wrapping_add(byte);
+    }
+
+    for &byte in data {
+        write!(srec, "{:02X}", byte).unwrap();
+        sum = sum.wrapping_add(byte);
+    }
+
+    // The checksum is the one's complement of the sum of the other bytes
+    writeln!(srec, "{:02X}", !sum).unwrap();
+}
+
+/// Generate a Motorola S-record file holding the words
+///
+/// The smallest address size that fits the last address is used: S1 records
+/// for 16 bit addresses, S2 for 24 bits, and S3 for 32 bits. The data records
+/// are counted with an S5 record, or an S6 record past 0xFFFF of them.
+pub fn generate_s_record(words: &[u32], options: &RecordOptions) -> String {
+    let bytes = word_bytes(words, options.endianness);
+
+    let last_address = options.base_address as u64 + cmp::max(bytes.len(), 1) as u64 - 1;
+    let (data_kind, end_kind, address_length) = if last_address <= 0xFFFF {
+        (1, 9, 2)
+    } else if last_address <= 0xFF_FFFF {
+        (2, 8, 3)
+    } else {
+        (3, 7, 4)
+    };
+    let record_length = options.record_length.clamp(1, 254 - address_length);
+
+    let mut srec = String::with_capacity(bytes.len() * 3);
+
+    // An empty header
+    write_s_record(&mut srec, 0, 0, 2, &[]);
+
+    let mut records = 0;
+    for (i, chunk) in bytes.chunks(record_length).enumerate() {
+        let address = options.base_address.wrapping_add((i * record_length) as u32);
+        write_s_record(&mut srec, data_kind, address, address_length, chunk);
+        records += 1;
+    }
+
+    // Count the data records, if there are few enough to count
+    if records <= 0xFFFF {
+        write_s_record(&mut srec, 5, records, 2, &[]);
+    } else if records <= 0xFF_FFFF {
+        write_s_record(&mut srec, 6, records, 3, &[]);
+    }
+
+    // End with the address to start running from
+    write_s_record(&mut srec, end_kind, options.base_address, address_length, &[]);
+
+    srec
+}
+
+/// How words are laid out in a raw binary file
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RawOptions {
+    pub endianness: Endianness,
+    /// Pad the file with `fill` bytes up to this many bytes
+    pub size: Option<usize>,
+    pub fill: u8,
+}
+
+impl Default for RawOptions {
+    fn default() -> RawOptions {
+        RawOptions {
+            endianness: Endianness::Little,
+            size: None,
+            fill: 0,
+        }
+    }
+}
+
+/// Generate a flat binary file holding 4 bytes for each word
+///
+/// Fails if the words do not fit in the requested size.
+pub fn generate_raw(words: &[u32], options: &RawOptions) -> Result<Vec<u8>, String> {
+    let mut bytes = word_bytes(words, options.endianness);
+
+    if let Some(size) = options.size {
+        if bytes.len() > size {
+            return Err(format!("program is {} bytes, more than the {} bytes requested", bytes.len(), size));
+        }
+
+        bytes.resize(size, options.fill);
+    }
+
+    Ok(bytes)
+}
+
+/// The shape of a block RAM initialized from a COE or MIF file
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct InitOptions {
+    /// The number of entries, padded with zeros, or just enough for the program if `None`
+    pub depth: Option<usize>,
+    /// The number of bits in each entry, either 8 or 16 to split each word
+    /// least significant part first, or at least 32 to hold one word each
+    pub width: u32,
+    pub radix: Radix,
+}
+
+impl Default for InitOptions {
+    fn default() -> InitOptions {
+        InitOptions {
+            depth: None,
+            width: 32,
+            radix: Radix::Hexadecimal,
+        }
+    }
+}
+
+impl InitOptions {
+    /// Split the words into entries and pad them to the depth
+    fn entries(&self, words: &[u32]) -> Result<Vec<u64>, String> {
+        let mut entries = Vec::with_capacity(words.len());
+
+        match self.width {
+            8 | 16 => for &word in words {
+                for i in 0..32 / self.width {
+                    entries.push(((word >> (i * self.width)) & ((1 << self.width) - 1)) as u64);
+                }
+            },
+            32..=64 => entries.extend(words.iter().map(|&word| word as u64)),
+            _ => return Err(format!("width {} is not 8, 16, or 32 to 64 bits", self.width)),
+        }
+
+        let depth = self.depth.unwrap_or(entries.len()).max(1);
+        if entries.len() > depth {
+            return Err(format!("program needs {} entries, more than the depth of {}", entries.len(), depth));
+        }
+        entries.resize(depth, 0);
+
+        Ok(entries)
+    }
+
+    /// Write an entry with all the digits for the width
+    fn entry(&self, entry: u64) -> String {
+        match self.radix {
+            Radix::Binary => format!("{:0width$b}", entry, width = self.width as usize),
+            Radix::Hexadecimal => format!("{:0width$X}", entry, width = (self.width as usize).div_ceil(4)),
+        }
+    }
+}
+
+/// Generate a Xilinx COE file to initialize a block RAM with the words
+pub fn generate_coe(words: &[u32], options: &InitOptions) -> Result<String, String> {
+    let entries = options.entries(words)?;
+    let mut coe = String::with_capacity(100 + entries.len() * (options.width as usize + 2));
+
+    let radix = match options.radix {
+        Radix::Binary => 2,
+        Radix::Hexadecimal => 16,
+    };
+    writeln!(coe, "memory_initialization_radix={};", radix).unwrap();
+    writeln!(coe, "memory_initialization_vector=").unwrap();
+
+    // Entries are separated by commas, and the last one ends the vector
+    for (i, &entry) in entries.iter().enumerate() {
+        let end = if i + 1 == entries.len() { ';' } else { ',' };
+        writeln!(coe, "{}{}", options.entry(entry), end).unwrap();
+    }
+
+    Ok(coe)
+}
+
+/// Generate an Intel (Altera) MIF file to initialize a block RAM with the words
+pub fn generate_mif(words: &[u32], options: &InitOptions) -> Result<String, String> {
+    let entries = options.entries(words)?;
+    let mut mif = String::with_capacity(100 + entries.len() * (options.width as usize + 10));
+
+    let radix = match options.radix {
+        Radix::Binary => "BIN",
+        Radix::Hexadecimal => "HEX",
+    };
+    writeln!(mif, "WIDTH={};", options.width).unwrap();
+    writeln!(mif, "DEPTH={};", entries.len()).unwrap();
+    writeln!(mif).unwrap();
+    writeln!(mif, "ADDRESS_RADIX=UNS;").unwrap();
+    writeln!(mif, "DATA_RADIX={};", radix).unwrap();
+    writeln!(mif).unwrap();
+    writeln!(mif, "CONTENT BEGIN").unwrap();
+
+    // Write each entry, collapsing runs of the same entry into address ranges
+    let mut start = 0;
+    while start < entries.len() {
+        let end = start + entries[start..].iter().take_while(|&&entry| entry == entries[start]).count() - 1;
+
+        if start == end {
+            writeln!(mif, "    {} : {};", start, options.entry(entries[start])).unwrap();
+        } else {
+            writeln!(mif, "    [{}..{}] : {};", start, end, options.entry(entries[start])).unwrap();
+        }
+
+        start = end + 1;
+    }
+
+    writeln!(mif, "END;").unwrap();
+
+    Ok(mif)
+}
+
+/// How a VHDL ROM looks up the word at each address
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum VhdlStyle {
+    /// Index a constant array of the words
+    Array,
+    /// Match the address with a case statement
+    Case,
+}
+
+impl RomOptions {
+    /// Write a word as a 32 bit VHDL literal
+    fn vhdl_literal(&self, word: u32) -> String {
+        match self.radix {
+            Radix::Binary => format!("\"{:032b}\"", word),
+            Radix::Hexadecimal => format!("x\"{:08X}\"", word),
+        }
+    }
+
+    /// Write the default word as a VHDL literal, ending the statement with
+    /// the instruction it is if there is one
+    fn write_vhdl_default(&self, rom: &mut String) {
+        write!(rom, "{};", self.vhdl_literal(self.default_word)).unwrap();
+        match disassembler::decode(self.default_word) {
+            Ok(instruction) => writeln!(rom, " -- {}", instruction).unwrap(),
+            Err(_) => rom.push('\n'),
+        }
+    }
+
+    /// The VHDL expression for the index of the word at `address`
+    fn vhdl_index(&self, address: &str) -> String {
+        match self.addressing {
+            Addressing::Word => format!("to_integer(unsigned({}))", address),
+            Addressing::Byte => format!(
+                "to_integer(unsigned({}({} downto 2)))", address, self.address_width - 1
+            ),
+        }
+    }
+}
+
+/// The widest address a VHDL `natural` can hold
+const MAX_VHDL_ADDRESS_WIDTH: u32 = 31;
+
+/// The words VHDL reserves, which can not name a port
+const VHDL_RESERVED: &[&str] = &[
+    "abs", "access", "after", "alias", "all", "and", "architecture", "array", "assert", "assume",
+    "attribute", "begin", "block", "body", "buffer", "bus", "case", "component", "configuration",
+    "constant", "context", "cover", "default", "disconnect", "downto", "else", "elsif", "end", "entity",
+    "exit", "fairness", "file", "for", "force", "function", "generate", "generic", "group", "guarded",
+    "if", "impure", "in", "inertial", "inout", "is", "label", "library", "linkage", "literal", "loop",
+    "map", "mod", "nand", "new", "next", "nor", "not", "null", "of", "on", "open", "or", "others", "out",
+    "package", "parameter", "port", "postponed", "procedure", "process", "property", "protected", "pure",
+    "range", "record", "register", "reject", "release", "rem", "report", "restrict", "return", "rol",
+    "ror", "select", "sequence", "severity", "shared", "signal", "sla", "sll", "sra", "srl", "strong",
+    "subtype", "then", "to", "transport", "type", "unaffected", "units", "until", "use", "variable",
+    "vmode", "vprop", "vunit", "wait", "when", "while", "with", "xnor", "xor",
+];
+
+/// The port name to use in VHDL, or `fallback` if `name` is reserved there
+fn vhdl_port<'a>(name: &'a str, fallback: &'a str) -> &'a str {
+    if VHDL_RESERVED.iter().any(|reserved| reserved.eq_ignore_ascii_case(name)) {
+        fallback
+    } else {
+        name
+    }
+}
+
+/// Generate a VHDL ROM entity shaped by `options` that outputs the word at each address
+///
+/// Ports named after VHDL reserved words, like the verilog default `out`, are
+/// renamed to `data`, `address` and `clock`. Byte addressing drops the bottom
+/// two bits of the address, so it needs an address at least 3 bits wide, and
+/// the address is converted to a `natural`, so it can be at most 31 bits wide.
+pub fn generate_vhdl_rom(words: &[u32], style: VhdlStyle, options: &RomOptions) -> Result<String, String> {
+    let minimum = match options.addressing {
+        Addressing::Word => 1,
+        Addressing::Byte => 3,
+    };
+    options.check_address_width(minimum, words.len())?;
+    if options.address_width > MAX_VHDL_ADDRESS_WIDTH {
+        return Err(format!(
+            "address width {} is too wide, expected at most {}", options.address_width, MAX_VHDL_ADDRESS_WIDTH
+        ));
+    }
+
+    let mut rom = String::with_capacity(800 + words.len() * 60);
+
+    let name = &options.module_name;
+    let data = vhdl_port(&options.data_port, "data");
+    let address = vhdl_port(&options.address_port, "address");
+    let clock = vhdl_port(&options.clock_port, "clock");
+
+    // Add the libraries and the entity
+    rom.push_str("library ieee;\nuse ieee.std_logic_1164.all;\nuse ieee.numeric_std.all;\n\n");
+    writeln!(rom, "entity {} is", name).unwrap();
+    writeln!(rom, "    port (").unwrap();
+    if options.clocked {
+        writeln!(rom, "        {} : in std_logic;", clock).unwrap();
+    }
+    writeln!(rom, "        {} : in std_logic_vector({} downto 0);", address, options.address_width - 1).unwrap();
+    writeln!(rom, "        {} : out std_logic_vector(31 downto 0)", data).unwrap();
+    writeln!(rom, "    );").unwrap();
+    writeln!(rom, "end {};\n", name).unwrap();
+
+    writeln!(rom, "architecture behavioral of {} is", name).unwrap();
+
+    // The statements that set the output, indented for inside a process
+    let mut lookup = String::new();
+
+    match style {
+        VhdlStyle::Array => {
+            // Name every word so one word arrays work, and fill an empty program with the default
+            let depth = words.len().max(1);
+            writeln!(rom, "    type rom_type is array (0 to {}) of std_logic_vector(31 downto 0);", depth - 1).unwrap();
+            writeln!(rom, "    constant words : rom_type := (").unwrap();
+            for (i, &word) in words.iter().enumerate() {
+                write!(rom, "        {} => {},", i, options.vhdl_literal(word)).unwrap();
+                match disassembler::decode(word) {
+                    Ok(instruction) => writeln!(rom, " -- {}", instruction).unwrap(),
+                    Err(_) => rom.push('\n'),
+                }
+            }
+            writeln!(rom, "        others => {}", options.vhdl_literal(options.default_word)).unwrap();
+            writeln!(rom, "    );").unwrap();
+            writeln!(rom, "    signal index : natural;").unwrap();
+            writeln!(rom, "begin").unwrap();
+            writeln!(rom, "    index <= {};", options.vhdl_index(address)).unwrap();
+
+            writeln!(lookup, "        if index < {} then", depth).unwrap();
+            writeln!(lookup, "            {} <= words(index);", data).unwrap();
+            writeln!(lookup, "        else").unwrap();
+            write!(lookup, "            {} <= ", data).unwrap();
+            options.write_vhdl_default(&mut lookup);
+            writeln!(lookup, "        end if;").unwrap();
+        }
+        VhdlStyle::Case => {
+            writeln!(rom, "begin").unwrap();
+
+            writeln!(lookup, "        case to_integer(unsigned({})) is", address).unwrap();
+            for (i, &word) in words.iter().enumerate() {
+                writeln!(lookup, "            when {} => {} <= {};", i * options.step(), data, options.vhdl_literal(word)).unwrap();
+            }
+            write!(lookup, "            when others => {} <= ", data).unwrap();
+            options.write_vhdl_default(&mut lookup);
+            writeln!(lookup, "        end case;").unwrap();
+        }
+    }
+
+    // Put the lookup in a process, inside a clock edge check if it is clocked
+    if options.clocked {
+        writeln!(rom, "    process ({})", clock).unwrap();
+        writeln!(rom, "    begin").unwrap();
+        writeln!(rom, "        if rising_edge({}) then", clock).unwrap();
+        for line in lookup.lines() {
+            writeln!(rom, "    {}", line).unwrap();
+        }
+        writeln!(rom, "        end if;").unwrap();
+    } else {
+        let sensitivity = match style {
+            VhdlStyle::Array => "index",
+            VhdlStyle::Case => address,
+        };
+        writeln!(rom, "    process ({})", sensitivity).unwrap();
+        writeln!(rom, "    begin").unwrap();
+        rom.push_str(&lookup);
+    }
+    writeln!(rom, "    end process;").unwrap();
+    writeln!(rom, "end behavioral;").unwrap();
+
+    Ok(rom)
+}
+
 #[test]
 fn test_generate_binary_str() {
     assert_eq!(
@@ -128,3 +829,501 @@ end
     )
 }
 
+#[test]
+fn test_generate_binary_str_words() {
+    assert_eq!(
+        generate_binary_str_words(&[0x5555555F, 0xAAAAAAA0]),
+"\
+01010101010101010101010101011111
+10101010101010101010101010100000
+"
+    );
+}
+
+#[test]
+fn test_generate_case_rom_words() {
+    assert_eq!(
+        generate_case_rom_words(&[0xF80320F7]),
+        "\
+module rom_case(out, address);
+    output reg [31:0] out;
+    input [15:0] address;
+    always @ (address) begin
+        case (address)
+            16'd0: out = 32'b11111000000000110010000011110111;
+            default: out = 32'hD60003E0; // BR XZR
+        endcase
+    end
+end
+"
+    )
+}
+
+#[test]
+fn test_generate_rom_default() {
+    assert_eq!(
+        generate_rom(&[0xF80320F7], &RomOptions::default()),
+        Ok("\
+module rom_case(out, address);
+    output reg [31:0] out;
+    input [15:0] address;
+    always @ (address) begin
+        case (address)
+            16'd0: out = 32'b11111000000000110010000011110111;
+            default: out = 32'b11010110000000000000001111100000; // BR XZR
+        endcase
+    end
+endmodule
+".to_owned())
+    )
+}
+
+#[test]
+fn test_generate_rom_options() {
+    let options = RomOptions {
+        module_name: "instruction_memory".to_owned(),
+        data_port: "instruction".to_owned(),
+        address_port: "pc".to_owned(),
+        clock_port: "clock".to_owned(),
+        address_width: 8,
+        addressing: Addressing::Byte,
+        default_word: 0x8B1F03FF,
+        clocked: true,
+        radix: Radix::Hexadecimal,
+    };
+
+    assert_eq!(
+        generate_rom(&[0xF80320F7, 0x910004E7], &options),
+        Ok("\
+module instruction_memory(instruction, clock, pc);
+    output reg [31:0] instruction;
+    input clock;
+    input [7:0] pc;
+    always @ (posedge clock) begin
+        case (pc)
+            8'd0: instruction <= 32'hF80320F7;
+            8'd4: instruction <= 32'h910004E7;
+            default: instruction <= 32'h8B1F03FF; // ADD XZR, XZR, XZR
+        endcase
+    end
+endmodule
+".to_owned())
+    )
+}
+
+#[test]
+fn test_generate_rom_no_address_bits() {
+    let options = RomOptions { address_width: 0, ..RomOptions::default() };
+
+    assert_eq!(
+        generate_rom(&[0xF80320F7], &options),
+        Err("address width 0 is too narrow, expected at least 1".to_owned())
+    );
+}
+
+#[test]
+fn test_generate_rom_too_many_words() {
+    let options = RomOptions { address_width: 2, ..RomOptions::default() };
+
+    assert!(generate_rom(&[0; 4], &options).is_ok());
+    assert_eq!(
+        generate_rom(&[0; 6], &options),
+        Err("6 words need address 5, which does not fit in an address width of 2".to_owned())
+    );
+    assert_eq!(
+        generate_rom(&[0; 2], &RomOptions { address_width: 2, addressing: Addressing::Byte, ..RomOptions::default() }),
+        Err("2 words need address 4, which does not fit in an address width of 2".to_owned())
+    );
+}
+
+#[test]
+fn test_generate_memory_hex() {
+    assert_eq!(
+        generate_memory(&[0xF80320F7, 0x910004E7], &MemoryOptions::default()),
+        "F80320F7\n910004E7\n"
+    )
+}
+
+#[test]
+fn test_generate_memory_binary_commented() {
+    let options = MemoryOptions { radix: Radix::Binary, addresses: true, disassembly: true };
+
+    assert_eq!(
+        generate_memory(&[0xF80320F7, 0xFFFFFFFF], &options),
+        "\
+@00000000 11111000000000110010000011110111 // STUR X23, [X7, #50]
+@00000001 11111111111111111111111111111111
+"
+    )
+}
+
+#[test]
+fn test_generate_commented_memory() {
+    let comments = vec![Some("store it".to_owned()), Some("not an instruction".to_owned()), None];
+
+    assert_eq!(
+        generate_commented_memory(&[0xF80320F7, 0xFFFFFFFF, 0x910004E7], &comments, &MemoryOptions::default()),
+        "F80320F7 // store it\nFFFFFFFF // not an instruction\n910004E7\n"
+    );
+
+    let options = MemoryOptions { disassembly: true, ..MemoryOptions::default() };
+    assert_eq!(
+        generate_commented_memory(&[0xF80320F7, 0xFFFFFFFF, 0x910004E7], &comments, &options),
+        "F80320F7 // STUR X23, [X7, #50]: store it\nFFFFFFFF // not an instruction\n910004E7 // ADDI X7, X7, 1\n"
+    );
+}
+
+#[test]
+fn test_generate_memory_module() {
+    let options = RomOptions { addressing: Addressing::Byte, radix: Radix::Hexadecimal, ..RomOptions::default() };
+
+    assert_eq!(
+        generate_memory_module("program.hex", 3, Radix::Hexadecimal, &options),
+        Ok("\
+module rom_case(out, address);
+    output reg [31:0] out;
+    input [15:0] address;
+    reg [31:0] memory [0:2];
+    initial $readmemh(\"program.hex\", memory);
+    always @ (address) begin
+        if (address < 12)
+            out = memory[address >> 2];
+        else
+            out = 32'hD60003E0; // BR XZR
+    end
+endmodule
+".to_owned())
+    )
+}
+
+#[test]
+fn test_generate_memory_module_no_address_bits() {
+    let options = RomOptions { address_width: 0, ..RomOptions::default() };
+
+    assert_eq!(
+        generate_memory_module("program.hex", 3, Radix::Hexadecimal, &options),
+        Err("address width 0 is too narrow, expected at least 1".to_owned())
+    );
+}
+
+#[test]
+fn test_generate_memory_module_too_deep() {
+    let options = RomOptions { address_width: 4, addressing: Addressing::Byte, ..RomOptions::default() };
+
+    assert!(generate_memory_module("program.hex", 4, Radix::Hexadecimal, &options).is_ok());
+    assert_eq!(
+        generate_memory_module("program.hex", 5, Radix::Hexadecimal, &options),
+        Err("5 words need address 16, which does not fit in an address width of 4".to_owned())
+    );
+}
+
+#[test]
+fn test_word_bytes() {
+    assert_eq!(word_bytes(&[0x12345678], Endianness::Little), vec![0x78, 0x56, 0x34, 0x12]);
+    assert_eq!(word_bytes(&[0x12345678], Endianness::Big), vec![0x12, 0x34, 0x56, 0x78]);
+}
+
+#[test]
+fn test_generate_intel_hex() {
+    let options = RecordOptions { base_address: 0x0100, ..RecordOptions::default() };
+
+    assert_eq!(
+        generate_intel_hex(&[0x36014621, 0x01472101, 0xFE7E0036, 0x0119D209, 0xD60003E0], &options),
+        "\
+:10010000214601360121470136007EFE09D2190140
+:04011000E00300D632
+:00000001FF
+"
+    );
+}
+
+#[test]
+fn test_generate_intel_hex_extended_address() {
+    let options = RecordOptions { base_address: 0x0001_FFFC, record_length: 8, endianness: Endianness::Big };
+
+    assert_eq!(
+        generate_intel_hex(&[0xF80320F7, 0x910004E7], &options),
+        "\
+:020000040001F9
+:04FFFC00F80320F7EF
+:020000040002F8
+:04000000910004E780
+:00000001FF
+"
+    );
+}
+
+#[test]
+fn test_generate_s_record() {
+    let options = RecordOptions { endianness: Endianness::Big, ..RecordOptions::default() };
+
+    assert_eq!(
+        generate_s_record(&[0x285F245F, 0x2212226A, 0x00042429, 0x0008237C], &options),
+        "\
+S0030000FC
+S1130000285F245F2212226A000424290008237C2A
+S5030001FB
+S9030000FC
+"
+    );
+}
+
+#[test]
+fn test_generate_s_record_long_address() {
+    let options = RecordOptions { base_address: 0x0010_0000, record_length: 4, endianness: Endianness::Little };
+
+    assert_eq!(
+        generate_s_record(&[0xF80320F7, 0x910004E7], &options),
+        "\
+S0030000FC
+S208100000F72003F8D5
+S208100004E704009167
+S5030002FA
+S804100000EB
+"
+    );
+}
+
+#[test]
+fn test_generate_s_record_many_records() {
+    let options = RecordOptions { base_address: 0, record_length: 1, endianness: Endianness::Little };
+    let srec = generate_s_record(&[0; 0x4001], &options);
+
+    assert_eq!(srec.lines().count(), 0x10004 + 3);
+    assert!(srec.ends_with("S20501000300F6\nS604010004F6\nS804000000FB\n"));
+}
+
+#[test]
+fn test_generate_raw() {
+    assert_eq!(
+        generate_raw(&[0xF80320F7, 0x910004E7], &RawOptions::default()),
+        Ok(vec![0xF7, 0x20, 0x03, 0xF8, 0xE7, 0x04, 0x00, 0x91])
+    );
+}
+
+#[test]
+fn test_generate_raw_padded() {
+    let options = RawOptions { endianness: Endianness::Big, size: Some(8), fill: 0xFF };
+
+    assert_eq!(
+        generate_raw(&[0xF80320F7], &options),
+        Ok(vec![0xF8, 0x03, 0x20, 0xF7, 0xFF, 0xFF, 0xFF, 0xFF])
+    );
+}
+
+#[test]
+fn test_generate_raw_too_large() {
+    let options = RawOptions { size: Some(4), ..RawOptions::default() };
+
+    assert_eq!(
+        generate_raw(&[0xF80320F7, 0x910004E7], &options),
+        Err("program is 8 bytes, more than the 4 bytes requested".to_owned())
+    );
+}
+
+#[test]
+fn test_generate_coe() {
+    let options = InitOptions { depth: Some(4), ..InitOptions::default() };
+
+    assert_eq!(
+        generate_coe(&[0xF80320F7, 0x910004E7], &options),
+        Ok("\
+memory_initialization_radix=16;
+memory_initialization_vector=
+F80320F7,
+910004E7,
+00000000,
+00000000;
+".to_owned())
+    );
+}
+
+#[test]
+fn test_generate_coe_bytes() {
+    let options = InitOptions { depth: None, width: 8, radix: Radix::Binary };
+
+    assert_eq!(
+        generate_coe(&[0xF80320F7], &options),
+        Ok("\
+memory_initialization_radix=2;
+memory_initialization_vector=
+11110111,
+00100000,
+00000011,
+11111000;
+".to_owned())
+    );
+}
+
+#[test]
+fn test_generate_mif() {
+    let options = InitOptions { depth: Some(8), width: 36, radix: Radix::Hexadecimal };
+
+    assert_eq!(
+        generate_mif(&[0xF80320F7, 0x910004E7, 0x910004E7], &options),
+        Ok("\
+WIDTH=36;
+DEPTH=8;
+
+ADDRESS_RADIX=UNS;
+DATA_RADIX=HEX;
+
+CONTENT BEGIN
+    0 : 0F80320F7;
+    [1..2] : 0910004E7;
+    [3..7] : 000000000;
+END;
+".to_owned())
+    );
+}
+
+#[test]
+fn test_generate_mif_too_deep() {
+    let options = InitOptions { depth: Some(1), ..InitOptions::default() };
+
+    assert_eq!(
+        generate_mif(&[0xF80320F7, 0x910004E7], &options),
+        Err("program needs 2 entries, more than the depth of 1".to_owned())
+    );
+}
+
+#[test]
+fn test_generate_mif_invalid_width() {
+    let options = InitOptions { width: 12, ..InitOptions::default() };
+
+    assert_eq!(
+        generate_mif(&[0xF80320F7], &options),
+        Err("width 12 is not 8, 16, or 32 to 64 bits".to_owned())
+    );
+}
+
+#[test]
+fn test_generate_vhdl_rom_case() {
+    let options = RomOptions { radix: Radix::Hexadecimal, ..RomOptions::default() };
+
+    assert_eq!(
+        generate_vhdl_rom(&[0xF80320F7, 0x910004E7], VhdlStyle::Case, &options),
+        Ok("\
+library ieee;
+use ieee.std_logic_1164.all;
+use ieee.numeric_std.all;
+
+entity rom_case is
+    port (
+        address : in std_logic_vector(15 downto 0);
+        data : out std_logic_vector(31 downto 0)
+    );
+end rom_case;
+
+architecture behavioral of rom_case is
+begin
+    process (address)
+    begin
+        case to_integer(unsigned(address)) is
+            when 0 => data <= x\"F80320F7\";
+            when 1 => data <= x\"910004E7\";
+            when others => data <= x\"D60003E0\"; -- BR XZR
+        end case;
+    end process;
+end behavioral;
+".to_owned())
+    )
+}
+
+#[test]
+fn test_generate_vhdl_rom_reserved_ports() {
+    let options = RomOptions {
+        address_port: "In".to_owned(),
+        clock_port: "signal".to_owned(),
+        clocked: true,
+        ..RomOptions::default()
+    };
+    let rom = generate_vhdl_rom(&[], VhdlStyle::Case, &options).unwrap();
+    assert!(rom.contains("        clock : in std_logic;\n"));
+    assert!(rom.contains("        address : in std_logic_vector(15 downto 0);\n"));
+    assert!(rom.contains("        data : out std_logic_vector(31 downto 0)\n"));
+    assert!(rom.contains("case to_integer(unsigned(address)) is"));
+    assert!(rom.contains("if rising_edge(clock) then"));
+}
+
+#[test]
+fn test_generate_vhdl_rom_array() {
+    let options = RomOptions {
+        module_name: "instruction_memory".to_owned(),
+        data_port: "instruction".to_owned(),
+        address_port: "pc".to_owned(),
+        clock_port: "clock".to_owned(),
+        address_width: 8,
+        addressing: Addressing::Byte,
+        default_word: 0x8B1F03FF,
+        clocked: true,
+        radix: Radix::Binary,
+    };
+
+    assert_eq!(
+        generate_vhdl_rom(&[0xF80320F7], VhdlStyle::Array, &options),
+        Ok("\
+library ieee;
+use ieee.std_logic_1164.all;
+use ieee.numeric_std.all;
+
+entity instruction_memory is
+    port (
+        clock : in std_logic;
+        pc : in std_logic_vector(7 downto 0);
+        instruction : out std_logic_vector(31 downto 0)
+    );
+end instruction_memory;
+
+architecture behavioral of instruction_memory is
+    type rom_type is array (0 to 0) of std_logic_vector(31 downto 0);
+    constant words : rom_type := (
+        0 => \"11111000000000110010000011110111\", -- STUR X23, [X7, #50]
+        others => \"10001011000111110000001111111111\"
+    );
+    signal index : natural;
+begin
+    index <= to_integer(unsigned(pc(7 downto 2)));
+    process (clock)
+    begin
+        if rising_edge(clock) then
+            if index < 1 then
+                instruction <= words(index);
+            else
+                instruction <= \"10001011000111110000001111111111\"; -- ADD XZR, XZR, XZR
+            end if;
+        end if;
+    end process;
+end behavioral;
+".to_owned())
+    )
+}
+
+#[test]
+fn test_generate_vhdl_rom_narrow_address() {
+    let options = RomOptions { address_width: 2, addressing: Addressing::Byte, ..RomOptions::default() };
+
+    assert_eq!(
+        generate_vhdl_rom(&[0xF80320F7], VhdlStyle::Array, &options),
+        Err("address width 2 is too narrow, expected at least 3".to_owned())
+    );
+    assert_eq!(
+        generate_vhdl_rom(&[0xF80320F7], VhdlStyle::Case, &RomOptions { address_width: 0, ..RomOptions::default() }),
+        Err("address width 0 is too narrow, expected at least 1".to_owned())
+    );
+}
+
+#[test]
+fn test_generate_vhdl_rom_address_width() {
+    let options = RomOptions { address_width: 1, ..RomOptions::default() };
+    assert_eq!(
+        generate_vhdl_rom(&[0; 3], VhdlStyle::Case, &options),
+        Err("3 words need address 2, which does not fit in an address width of 1".to_owned())
+    );
+
+    assert!(generate_vhdl_rom(&[0], VhdlStyle::Array, &RomOptions { address_width: 31, ..RomOptions::default() }).is_ok());
+    assert_eq!(
+        generate_vhdl_rom(&[0], VhdlStyle::Array, &RomOptions { address_width: 32, ..RomOptions::default() }),
+        Err("address width 32 is too wide, expected at most 31".to_owned())
+    );
+}
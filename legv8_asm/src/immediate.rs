@@ -1,114 +1,294 @@
+use std::fmt;
+
 use bit::Bit;
 
-#[derive(Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct Immediate6(pub i8);
 
+impl Immediate6 {
+    /// The smallest value an unsigned 6 bit shift amount can hold
+    pub const MIN: i64 = 0;
+    /// The largest value an unsigned 6 bit shift amount can hold
+    pub const MAX: i64 = 63;
+
+    /// Make an immediate from `number`, or `None` if it does not fit
+    pub fn new(number: i64) -> Option<Immediate6> {
+        if (Self::MIN..=Self::MAX).contains(&number) {
+            Some(Immediate6(number as i8))
+        } else {
+            None
+        }
+    }
+}
+
 impl From<i8> for Immediate6 {
     fn from(number: i8) -> Immediate6 {
         Immediate6(number)
     }
 }
 
+impl fmt::Display for Immediate6 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The immediate's bits in the bottom 6 bits of a word
+impl From<Immediate6> for u32 {
+    fn from(immediate: Immediate6) -> u32 {
+        immediate.0 as u32 & 0x3F
+    }
+}
+
 impl From<Immediate6> for [Bit; 6] {
     fn from(immediate: Immediate6) -> [Bit; 6] {
         let mut result: [Bit; 6] = [Bit::Zero; 6];
-        for i in 0..6 {
-            result[i] = Bit::from_u8((immediate.0 >> i) as u8 & 1_u8).unwrap();
+        for (i, bit) in result.iter_mut().enumerate() {
+            *bit = Bit::from_u8((immediate.0 >> i) as u8 & 1_u8).unwrap();
         }
         result
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct Immediate9(pub i16);
 
+impl Immediate9 {
+    /// The smallest value a signed 9 bit offset can hold
+    pub const MIN: i64 = -256;
+    /// The largest value a signed 9 bit offset can hold
+    pub const MAX: i64 = 255;
+
+    /// Make an immediate from `number`, or `None` if it does not fit
+    pub fn new(number: i64) -> Option<Immediate9> {
+        if (Self::MIN..=Self::MAX).contains(&number) {
+            Some(Immediate9(number as i16))
+        } else {
+            None
+        }
+    }
+}
+
 impl From<i16> for Immediate9 {
     fn from(number: i16) -> Immediate9 {
         Immediate9(number)
     }
 }
 
+impl fmt::Display for Immediate9 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The immediate's bits in the bottom 9 bits of a word
+impl From<Immediate9> for u32 {
+    fn from(immediate: Immediate9) -> u32 {
+        immediate.0 as u32 & 0x1FF
+    }
+}
+
 impl From<Immediate9> for [Bit; 9] {
     fn from(immediate: Immediate9) -> [Bit; 9] {
         let mut result: [Bit; 9] = [Bit::Zero; 9];
-        for i in 0..9 {
-            result[i] = Bit::from_u8((immediate.0 >> i) as u8 & 1_u8).unwrap();
+        for (i, bit) in result.iter_mut().enumerate() {
+            *bit = Bit::from_u8((immediate.0 >> i) as u8 & 1_u8).unwrap();
         }
         result
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct Immediate12(pub i16);
 
+impl Immediate12 {
+    /// The smallest value an unsigned 12 bit immediate can hold
+    pub const MIN: i64 = 0;
+    /// The largest value an unsigned 12 bit immediate can hold
+    pub const MAX: i64 = 4095;
+
+    /// Make an immediate from `number`, or `None` if it does not fit
+    pub fn new(number: i64) -> Option<Immediate12> {
+        if (Self::MIN..=Self::MAX).contains(&number) {
+            Some(Immediate12(number as i16))
+        } else {
+            None
+        }
+    }
+}
+
 impl From<i16> for Immediate12 {
     fn from(number: i16) -> Immediate12 {
         Immediate12(number)
     }
 }
 
+impl fmt::Display for Immediate12 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The immediate's bits in the bottom 12 bits of a word
+impl From<Immediate12> for u32 {
+    fn from(immediate: Immediate12) -> u32 {
+        immediate.0 as u32 & 0xFFF
+    }
+}
+
 impl From<Immediate12> for [Bit; 12] {
     fn from(immediate: Immediate12) -> [Bit; 12] {
         let mut result: [Bit; 12] = [Bit::Zero; 12];
-        for i in 0..12 {
-            result[i] = Bit::from_u8((immediate.0 >> i) as u8 & 1_u8).unwrap();
+        for (i, bit) in result.iter_mut().enumerate() {
+            *bit = Bit::from_u8((immediate.0 >> i) as u8 & 1_u8).unwrap();
         }
         result
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct Immediate16(pub i16);
 
+impl Immediate16 {
+    /// The smallest value an unsigned 16 bit immediate can hold
+    pub const MIN: i64 = 0;
+    /// The largest value an unsigned 16 bit immediate can hold
+    pub const MAX: i64 = 65535;
+
+    /// Make an immediate from `number`, or `None` if it does not fit
+    ///
+    /// Values above `i16::MAX` are kept as the same 16 bits, so they come out
+    /// negative when read back from the field.
+    pub fn new(number: i64) -> Option<Immediate16> {
+        if (Self::MIN..=Self::MAX).contains(&number) {
+            Some(Immediate16(number as u16 as i16))
+        } else {
+            None
+        }
+    }
+}
+
 impl From<i16> for Immediate16 {
     fn from(number: i16) -> Immediate16 {
         Immediate16(number)
     }
 }
 
+/// Format the immediate as the unsigned number it was made from
+impl fmt::Display for Immediate16 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0 as u16)
+    }
+}
+
+/// The immediate's bits in the bottom 16 bits of a word
+impl From<Immediate16> for u32 {
+    fn from(immediate: Immediate16) -> u32 {
+        immediate.0 as u32 & 0xFFFF
+    }
+}
+
 impl From<Immediate16> for [Bit; 16] {
     fn from(immediate: Immediate16) -> [Bit; 16] {
         let mut result: [Bit; 16] = [Bit::Zero; 16];
-        for i in 0..16 {
-            result[i] = Bit::from_u8((immediate.0 >> i) as u8 & 1_u8).unwrap();
+        for (i, bit) in result.iter_mut().enumerate() {
+            *bit = Bit::from_u8((immediate.0 >> i) as u8 & 1_u8).unwrap();
         }
         result
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct Immediate19(pub i32);
 
+impl Immediate19 {
+    /// The smallest value a signed 19 bit offset can hold
+    pub const MIN: i64 = -262_144;
+    /// The largest value a signed 19 bit offset can hold
+    pub const MAX: i64 = 262_143;
+
+    /// Make an immediate from `number`, or `None` if it does not fit
+    pub fn new(number: i64) -> Option<Immediate19> {
+        if (Self::MIN..=Self::MAX).contains(&number) {
+            Some(Immediate19(number as i32))
+        } else {
+            None
+        }
+    }
+}
+
 impl From<i32> for Immediate19 {
     fn from(number: i32) -> Immediate19 {
         Immediate19(number)
     }
 }
 
+impl fmt::Display for Immediate19 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The immediate's bits in the bottom 19 bits of a word
+impl From<Immediate19> for u32 {
+    fn from(immediate: Immediate19) -> u32 {
+        immediate.0 as u32 & 0x7_FFFF
+    }
+}
+
 impl From<Immediate19> for [Bit; 19] {
     fn from(immediate: Immediate19) -> [Bit; 19] {
         let mut result: [Bit; 19] = [Bit::Zero; 19];
-        for i in 0..19 {
-            result[i] = Bit::from_u8((immediate.0 >> i) as u8 & 1_u8).unwrap();
+        for (i, bit) in result.iter_mut().enumerate() {
+            *bit = Bit::from_u8((immediate.0 >> i) as u8 & 1_u8).unwrap();
         }
         result
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct Immediate26(pub i32);
 
+impl Immediate26 {
+    /// The smallest value a signed 26 bit offset can hold
+    pub const MIN: i64 = -33_554_432;
+    /// The largest value a signed 26 bit offset can hold
+    pub const MAX: i64 = 33_554_431;
+
+    /// Make an immediate from `number`, or `None` if it does not fit
+    pub fn new(number: i64) -> Option<Immediate26> {
+        if (Self::MIN..=Self::MAX).contains(&number) {
+            Some(Immediate26(number as i32))
+        } else {
+            None
+        }
+    }
+}
+
 impl From<i32> for Immediate26 {
     fn from(number: i32) -> Immediate26 {
         Immediate26(number)
     }
 }
 
+impl fmt::Display for Immediate26 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The immediate's bits in the bottom 26 bits of a word
+impl From<Immediate26> for u32 {
+    fn from(immediate: Immediate26) -> u32 {
+        immediate.0 as u32 & 0x3FF_FFFF
+    }
+}
+
 impl From<Immediate26> for [Bit; 26] {
     fn from(immediate: Immediate26) -> [Bit; 26] {
         let mut result: [Bit; 26] = [Bit::Zero; 26];
-        for i in 0..26 {
-            result[i] = Bit::from_u8((immediate.0 >> i) as u8 & 1_u8).unwrap();
+        for (i, bit) in result.iter_mut().enumerate() {
+            *bit = Bit::from_u8((immediate.0 >> i) as u8 & 1_u8).unwrap();
         }
         result
     }
@@ -174,4 +354,59 @@ fn test_negative_immediate26() {
     assert_eq!(<[Bit; 26]>::from(Immediate26(-5)), bit_array!(1, 1, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1));
 }
 
+#[test]
+fn test_immediate6_new() {
+    assert_eq!(Immediate6::new(63), Some(Immediate6(63)));
+    assert_eq!(Immediate6::new(64), None);
+    assert_eq!(Immediate6::new(-1), None);
+}
 
+#[test]
+fn test_immediate9_new() {
+    assert_eq!(Immediate9::new(-256), Some(Immediate9(-256)));
+    assert_eq!(Immediate9::new(255), Some(Immediate9(255)));
+    assert_eq!(Immediate9::new(256), None);
+    assert_eq!(Immediate9::new(-257), None);
+}
+
+#[test]
+fn test_immediate12_new() {
+    assert_eq!(Immediate12::new(4095), Some(Immediate12(4095)));
+    assert_eq!(Immediate12::new(5000), None);
+    assert_eq!(Immediate12::new(-1), None);
+}
+
+#[test]
+fn test_immediate16_new() {
+    assert_eq!(Immediate16::new(0xBEEF), Some(Immediate16(0xBEEF_u16 as i16)));
+    assert_eq!(Immediate16::new(65536), None);
+    assert_eq!(Immediate16::new(-1), None);
+}
+
+#[test]
+fn test_immediate19_new() {
+    assert_eq!(Immediate19::new(-262_144), Some(Immediate19(-262_144)));
+    assert_eq!(Immediate19::new(262_144), None);
+}
+
+#[test]
+fn test_immediate26_new() {
+    assert_eq!(Immediate26::new(33_554_431), Some(Immediate26(33_554_431)));
+    assert_eq!(Immediate26::new(-33_554_433), None);
+}
+
+#[test]
+fn test_immediate_display() {
+    assert_eq!(Immediate9(-5).to_string(), "-5");
+    assert_eq!(Immediate16(0xBEEF_u16 as i16).to_string(), "48879");
+}
+
+#[test]
+fn test_negative_immediate9_u32() {
+    assert_eq!(u32::from(Immediate9(-5)), 0x1FB);
+}
+
+#[test]
+fn test_immediate16_u32() {
+    assert_eq!(u32::from(Immediate16(0xBEEF_u16 as i16)), 0xBEEF);
+}
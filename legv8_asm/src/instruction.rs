@@ -1,5 +1,9 @@
+use std::fmt;
+
+use bit;
 use bit::Bit;
 use register::Register;
+use register::FloatRegister;
 use shift16::Shift16;
 use condition::Condition;
 use immediate::Immediate6;
@@ -9,16 +13,19 @@ use immediate::Immediate16;
 use immediate::Immediate19;
 use immediate::Immediate26;
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Instruction {
     Add {
         n: Register,
         m: Register,
+        /// How far left to shift `m` before using it
+        shift: Immediate6,
         destination: Register
     },
     Subtract {
         n: Register,
         m: Register,
+        shift: Immediate6,
         destination: Register
     },
 
@@ -77,6 +84,43 @@ pub enum Instruction {
         data: Register
     },
 
+    /// Store the lower 32 bits of the register
+    StoreWord {
+        address: Register,
+        offset: Immediate9,
+        data: Register
+    },
+    /// Load 32 bits, sign extended to 64
+    LoadSignedWord {
+        address: Register,
+        offset: Immediate9,
+        data: Register
+    },
+
+    StoreHalfword {
+        address: Register,
+        offset: Immediate9,
+        data: Register
+    },
+    LoadHalfword {
+        address: Register,
+        offset: Immediate9,
+        data: Register
+    },
+
+    /// Load and mark the address for a following `StoreExclusive`
+    LoadExclusive {
+        address: Register,
+        data: Register
+    },
+    /// Store only if nothing else has written to the address since the
+    /// `LoadExclusive`, setting `status` to 0 if the store happened
+    StoreExclusive {
+        address: Register,
+        data: Register,
+        status: Register
+    },
+
     MoveZero {
         immediate: Immediate16,
         shift: Shift16,
@@ -91,16 +135,19 @@ pub enum Instruction {
     And {
         n: Register,
         m: Register,
+        shift: Immediate6,
         destination: Register
     },
     Or {
         n: Register,
         m: Register,
+        shift: Immediate6,
         destination: Register
     },
     Xor {
         n: Register,
         m: Register,
+        shift: Immediate6,
         destination: Register
     },
 
@@ -142,6 +189,104 @@ pub enum Instruction {
         destination: Register
     },
 
+    Multiply {
+        n: Register,
+        m: Register,
+        destination: Register
+    },
+    /// The upper 64 bits of the signed 128 bit product
+    SignedMultiplyHigh {
+        n: Register,
+        m: Register,
+        destination: Register
+    },
+    /// The upper 64 bits of the unsigned 128 bit product
+    UnsignedMultiplyHigh {
+        n: Register,
+        m: Register,
+        destination: Register
+    },
+    SignedDivide {
+        n: Register,
+        m: Register,
+        destination: Register
+    },
+    UnsignedDivide {
+        n: Register,
+        m: Register,
+        destination: Register
+    },
+
+    FloatAddSingle {
+        n: FloatRegister,
+        m: FloatRegister,
+        destination: FloatRegister
+    },
+    FloatAddDouble {
+        n: FloatRegister,
+        m: FloatRegister,
+        destination: FloatRegister
+    },
+    FloatSubtractSingle {
+        n: FloatRegister,
+        m: FloatRegister,
+        destination: FloatRegister
+    },
+    FloatSubtractDouble {
+        n: FloatRegister,
+        m: FloatRegister,
+        destination: FloatRegister
+    },
+    FloatMultiplySingle {
+        n: FloatRegister,
+        m: FloatRegister,
+        destination: FloatRegister
+    },
+    FloatMultiplyDouble {
+        n: FloatRegister,
+        m: FloatRegister,
+        destination: FloatRegister
+    },
+    FloatDivideSingle {
+        n: FloatRegister,
+        m: FloatRegister,
+        destination: FloatRegister
+    },
+    FloatDivideDouble {
+        n: FloatRegister,
+        m: FloatRegister,
+        destination: FloatRegister
+    },
+    /// Compare, setting the flags
+    FloatCompareSingle {
+        n: FloatRegister,
+        m: FloatRegister
+    },
+    FloatCompareDouble {
+        n: FloatRegister,
+        m: FloatRegister
+    },
+    StoreSingle {
+        address: Register,
+        offset: Immediate9,
+        data: FloatRegister
+    },
+    LoadSingle {
+        address: Register,
+        offset: Immediate9,
+        data: FloatRegister
+    },
+    StoreDouble {
+        address: Register,
+        offset: Immediate9,
+        data: FloatRegister
+    },
+    LoadDouble {
+        address: Register,
+        offset: Immediate9,
+        data: FloatRegister
+    },
+
     CompareBranchZero {
         address: Immediate19,
         r: Register
@@ -167,295 +312,483 @@ pub enum Instruction {
     }
 }
 
+/// Format the instruction as LEGv8 assembly that `parser::parse_lines` accepts
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Add { ref n, ref m, shift, ref destination } =>
+                write_shifted(f, "ADD", destination, n, m, shift),
+            Instruction::Subtract { ref n, ref m, shift, ref destination } =>
+                write_shifted(f, "SUB", destination, n, m, shift),
+            Instruction::AddImmediate { ref n, ref m, ref destination } =>
+                write!(f, "ADDI {}, {}, {}", destination, n, m),
+            Instruction::SubtractImmediate { ref n, ref m, ref destination } =>
+                write!(f, "SUBI {}, {}, {}", destination, n, m),
+            Instruction::AddSetFlags { ref n, ref m, ref destination } =>
+                write!(f, "ADDS {}, {}, {}", destination, n, m),
+            Instruction::SubtractSetFlags { ref n, ref m, ref destination } =>
+                write!(f, "SUBS {}, {}, {}", destination, n, m),
+            Instruction::AddImmediateSetFlags { ref n, ref m, ref destination } =>
+                write!(f, "ADDIS {}, {}, {}", destination, n, m),
+            Instruction::SubtractImmediateSetFlags { ref n, ref m, ref destination } =>
+                write!(f, "SUBIS {}, {}, {}", destination, n, m),
+            Instruction::Store { ref address, ref offset, ref data } =>
+                write!(f, "STUR {}, [{}, #{}]", data, address, offset),
+            Instruction::Load { ref address, ref offset, ref data } =>
+                write!(f, "LDUR {}, [{}, #{}]", data, address, offset),
+            Instruction::StoreByte { ref address, ref offset, ref data } =>
+                write!(f, "STURB {}, [{}, #{}]", data, address, offset),
+            Instruction::LoadByte { ref address, ref offset, ref data } =>
+                write!(f, "LDURB {}, [{}, #{}]", data, address, offset),
+            Instruction::StoreWord { ref address, ref offset, ref data } =>
+                write!(f, "STURW {}, [{}, #{}]", data, address, offset),
+            Instruction::LoadSignedWord { ref address, ref offset, ref data } =>
+                write!(f, "LDURSW {}, [{}, #{}]", data, address, offset),
+            Instruction::StoreHalfword { ref address, ref offset, ref data } =>
+                write!(f, "STURH {}, [{}, #{}]", data, address, offset),
+            Instruction::LoadHalfword { ref address, ref offset, ref data } =>
+                write!(f, "LDURH {}, [{}, #{}]", data, address, offset),
+            Instruction::LoadExclusive { ref address, ref data } =>
+                write!(f, "LDXR {}, [{}, #0]", data, address),
+            Instruction::StoreExclusive { ref address, ref data, ref status } =>
+                write!(f, "STXR {}, {}, [{}, #0]", data, status, address),
+            Instruction::MoveZero { ref immediate, ref shift, ref destination } =>
+                write!(f, "MOVZ {}, {}, {}", destination, immediate, shift),
+            Instruction::MoveKeep { ref immediate, ref shift, ref destination } =>
+                write!(f, "MOVK {}, {}, {}", destination, immediate, shift),
+            Instruction::And { ref n, ref m, shift, ref destination } =>
+                write_shifted(f, "AND", destination, n, m, shift),
+            Instruction::Or { ref n, ref m, shift, ref destination } =>
+                write_shifted(f, "ORR", destination, n, m, shift),
+            Instruction::Xor { ref n, ref m, shift, ref destination } =>
+                write_shifted(f, "EOR", destination, n, m, shift),
+            Instruction::AndImmediate { ref n, ref m, ref destination } =>
+                write!(f, "ANDI {}, {}, {}", destination, n, m),
+            Instruction::OrImmediate { ref n, ref m, ref destination } =>
+                write!(f, "ORRI {}, {}, {}", destination, n, m),
+            Instruction::XorImmediate { ref n, ref m, ref destination } =>
+                write!(f, "EORI {}, {}, {}", destination, n, m),
+            Instruction::AndSetFlags { ref n, ref m, ref destination } =>
+                write!(f, "ANDS {}, {}, {}", destination, n, m),
+            Instruction::AndImmediateSetFlags { ref n, ref m, ref destination } =>
+                write!(f, "ANDIS {}, {}, {}", destination, n, m),
+            Instruction::LogicalShiftRight { ref n, ref m, ref destination } =>
+                write!(f, "LSR {}, {}, {}", destination, n, m),
+            Instruction::LogicalShiftLeft { ref n, ref m, ref destination } =>
+                write!(f, "LSL {}, {}, {}", destination, n, m),
+            Instruction::Multiply { ref n, ref m, ref destination } =>
+                write!(f, "MUL {}, {}, {}", destination, n, m),
+            Instruction::SignedMultiplyHigh { ref n, ref m, ref destination } =>
+                write!(f, "SMULH {}, {}, {}", destination, n, m),
+            Instruction::UnsignedMultiplyHigh { ref n, ref m, ref destination } =>
+                write!(f, "UMULH {}, {}, {}", destination, n, m),
+            Instruction::SignedDivide { ref n, ref m, ref destination } =>
+                write!(f, "SDIV {}, {}, {}", destination, n, m),
+            Instruction::UnsignedDivide { ref n, ref m, ref destination } =>
+                write!(f, "UDIV {}, {}, {}", destination, n, m),
+            Instruction::FloatAddSingle { n, m, destination } =>
+                write!(f, "FADDS S{}, S{}, S{}", destination as u8, n as u8, m as u8),
+            Instruction::FloatAddDouble { n, m, destination } =>
+                write!(f, "FADDD D{}, D{}, D{}", destination as u8, n as u8, m as u8),
+            Instruction::FloatSubtractSingle { n, m, destination } =>
+                write!(f, "FSUBS S{}, S{}, S{}", destination as u8, n as u8, m as u8),
+            Instruction::FloatSubtractDouble { n, m, destination } =>
+                write!(f, "FSUBD D{}, D{}, D{}", destination as u8, n as u8, m as u8),
+            Instruction::FloatMultiplySingle { n, m, destination } =>
+                write!(f, "FMULS S{}, S{}, S{}", destination as u8, n as u8, m as u8),
+            Instruction::FloatMultiplyDouble { n, m, destination } =>
+                write!(f, "FMULD D{}, D{}, D{}", destination as u8, n as u8, m as u8),
+            Instruction::FloatDivideSingle { n, m, destination } =>
+                write!(f, "FDIVS S{}, S{}, S{}", destination as u8, n as u8, m as u8),
+            Instruction::FloatDivideDouble { n, m, destination } =>
+                write!(f, "FDIVD D{}, D{}, D{}", destination as u8, n as u8, m as u8),
+            Instruction::FloatCompareSingle { n, m } =>
+                write!(f, "FCMPS S{}, S{}", n as u8, m as u8),
+            Instruction::FloatCompareDouble { n, m } =>
+                write!(f, "FCMPD D{}, D{}", n as u8, m as u8),
+            Instruction::StoreSingle { ref address, ref offset, data } =>
+                write!(f, "STURS S{}, [{}, #{}]", data as u8, address, offset),
+            Instruction::LoadSingle { ref address, ref offset, data } =>
+                write!(f, "LDURS S{}, [{}, #{}]", data as u8, address, offset),
+            Instruction::StoreDouble { ref address, ref offset, data } =>
+                write!(f, "STURD D{}, [{}, #{}]", data as u8, address, offset),
+            Instruction::LoadDouble { ref address, ref offset, data } =>
+                write!(f, "LDURD D{}, [{}, #{}]", data as u8, address, offset),
+            Instruction::CompareBranchZero { ref address, ref r } =>
+                write!(f, "CBZ {}, {}", r, address),
+            Instruction::CompareBranchNotZero { ref address, ref r } =>
+                write!(f, "CBNZ {}, {}", r, address),
+            Instruction::ConditionalBranch { ref address, ref condition } =>
+                write!(f, "B.{} {}", condition, address),
+            Instruction::Branch { ref address } =>
+                write!(f, "B {}", address),
+            Instruction::BranchRegister { ref r } =>
+                write!(f, "BR {}", r),
+            Instruction::BranchLink { ref address } =>
+                write!(f, "BL {}", address),
+        }
+    }
+}
+
+/// Write an instruction with three registers, adding the shift of the last
+/// one if there is any
+fn write_shifted(
+    f: &mut fmt::Formatter,
+    mnemonic: &str,
+    destination: &Register,
+    n: &Register,
+    m: &Register,
+    shift: Immediate6,
+) -> fmt::Result {
+    write!(f, "{} {}, {}, {}", mnemonic, destination, n, m)?;
+    if shift.0 != 0 {
+        write!(f, ", LSL {}", shift)?;
+    }
+    Ok(())
+}
+
+fn r_format(opcode: u32, m: u32, shamt: u32, n: Register, d: Register) -> u32 {
+    opcode << 21 | m << 16 | shamt << 10 | u32::from(n) << 5 | u32::from(d)
+}
+
+fn i_format(opcode: u32, immediate: Immediate12, n: Register, d: Register) -> u32 {
+    opcode << 22 | u32::from(immediate) << 10 | u32::from(n) << 5 | u32::from(d)
+}
+
+/// The R-format for floating point registers, with `d` a number so that
+/// compares can leave it zero
+fn float_format(opcode: u32, m: FloatRegister, shamt: u32, n: FloatRegister, d: u32) -> u32 {
+    opcode << 21 | u32::from(m) << 16 | shamt << 10 | u32::from(n) << 5 | d
+}
+
+fn d_format<T: Into<u32>>(opcode: u32, offset: Immediate9, n: Register, t: T) -> u32 {
+    opcode << 21 | u32::from(offset) << 12 | u32::from(n) << 5 | t.into()
+}
+
+/// The D-format with the status register in place of the offset, and the
+/// unused bits set as on A64
+fn exclusive_format(opcode: u32, s: Register, n: Register, t: Register) -> u32 {
+    opcode << 21 | u32::from(s) << 16 | 0b11111 << 10 | u32::from(n) << 5 | u32::from(t)
+}
+
+fn iw_format(opcode: u32, immediate: Immediate16, shift: Shift16, d: Register) -> u32 {
+    opcode << 23 | u32::from(shift) << 21 | u32::from(immediate) << 5 | u32::from(d)
+}
+
+fn b_format(opcode: u32, address: Immediate26) -> u32 {
+    opcode << 26 | u32::from(address)
+}
+
+fn cb_format(opcode: u32, address: Immediate19, t: u32) -> u32 {
+    opcode << 24 | u32::from(address) << 5 | t
+}
+
+impl Instruction {
+    /// Encode the instruction into its 32 bit machine code
+    pub fn encode(&self) -> u32 {
+        match *self {
+            Instruction::Add { n, m, shift, destination } =>
+                r_format(0b10001011000, m.into(), shift.into(), n, destination),
+            Instruction::Subtract { n, m, shift, destination } =>
+                r_format(0b11001011000, m.into(), shift.into(), n, destination),
+            Instruction::AddImmediate { n, m, destination } =>
+                i_format(0b1001000100, m, n, destination),
+            Instruction::SubtractImmediate { n, m, destination } =>
+                i_format(0b1101000100, m, n, destination),
+            Instruction::AddSetFlags { n, m, destination } =>
+                r_format(0b10101011000, m.into(), 0, n, destination),
+            Instruction::SubtractSetFlags { n, m, destination } =>
+                r_format(0b11101011000, m.into(), 0, n, destination),
+            Instruction::AddImmediateSetFlags { n, m, destination } =>
+                i_format(0b1011000100, m, n, destination),
+            Instruction::SubtractImmediateSetFlags { n, m, destination } =>
+                i_format(0b1111000100, m, n, destination),
+            Instruction::Store { address, offset, data } =>
+                d_format(0b11111000000, offset, address, data),
+            Instruction::Load { address, offset, data } =>
+                d_format(0b11111000010, offset, address, data),
+            Instruction::StoreByte { address, offset, data } =>
+                d_format(0b00111000000, offset, address, data),
+            Instruction::LoadByte { address, offset, data } =>
+                d_format(0b00111000010, offset, address, data),
+            Instruction::StoreWord { address, offset, data } =>
+                d_format(0b10111000000, offset, address, data),
+            Instruction::LoadSignedWord { address, offset, data } =>
+                d_format(0b10111000100, offset, address, data),
+            Instruction::StoreHalfword { address, offset, data } =>
+                d_format(0b01111000000, offset, address, data),
+            Instruction::LoadHalfword { address, offset, data } =>
+                d_format(0b01111000010, offset, address, data),
+            Instruction::LoadExclusive { address, data } =>
+                exclusive_format(0b11001000010, Register::XZR, address, data),
+            Instruction::StoreExclusive { address, data, status } =>
+                exclusive_format(0b11001000000, status, address, data),
+            Instruction::MoveZero { immediate, shift, destination } =>
+                iw_format(0b110100101, immediate, shift, destination),
+            Instruction::MoveKeep { immediate, shift, destination } =>
+                iw_format(0b111100101, immediate, shift, destination),
+            Instruction::And { n, m, shift, destination } =>
+                r_format(0b10001010000, m.into(), shift.into(), n, destination),
+            Instruction::Or { n, m, shift, destination } =>
+                r_format(0b10101010000, m.into(), shift.into(), n, destination),
+            Instruction::Xor { n, m, shift, destination } =>
+                r_format(0b11001010000, m.into(), shift.into(), n, destination),
+            Instruction::AndImmediate { n, m, destination } =>
+                i_format(0b1001001000, m, n, destination),
+            Instruction::OrImmediate { n, m, destination } =>
+                i_format(0b1011001000, m, n, destination),
+            Instruction::XorImmediate { n, m, destination } =>
+                i_format(0b1101001000, m, n, destination),
+            Instruction::AndSetFlags { n, m, destination } =>
+                r_format(0b11101010000, m.into(), 0, n, destination),
+            Instruction::AndImmediateSetFlags { n, m, destination } =>
+                i_format(0b1111001000, m, n, destination),
+            Instruction::LogicalShiftRight { n, m, destination } =>
+                r_format(0b11010011010, 0, m.into(), n, destination),
+            Instruction::LogicalShiftLeft { n, m, destination } =>
+                r_format(0b11010011011, 0, m.into(), n, destination),
+            // The multiplies have all ones in the shamt field, and the divides
+            // tell signed and unsigned apart by it
+            Instruction::Multiply { n, m, destination } =>
+                r_format(0b10011011000, m.into(), 0b011111, n, destination),
+            Instruction::SignedMultiplyHigh { n, m, destination } =>
+                r_format(0b10011011010, m.into(), 0b011111, n, destination),
+            Instruction::UnsignedMultiplyHigh { n, m, destination } =>
+                r_format(0b10011011110, m.into(), 0b011111, n, destination),
+            Instruction::SignedDivide { n, m, destination } =>
+                r_format(0b10011010110, m.into(), 0b000010, n, destination),
+            Instruction::UnsignedDivide { n, m, destination } =>
+                r_format(0b10011010110, m.into(), 0b000011, n, destination),
+            Instruction::FloatAddSingle { n, m, destination } =>
+                float_format(0b00011110001, m, 0b001010, n, destination.into()),
+            Instruction::FloatAddDouble { n, m, destination } =>
+                float_format(0b00011110011, m, 0b001010, n, destination.into()),
+            Instruction::FloatSubtractSingle { n, m, destination } =>
+                float_format(0b00011110001, m, 0b001110, n, destination.into()),
+            Instruction::FloatSubtractDouble { n, m, destination } =>
+                float_format(0b00011110011, m, 0b001110, n, destination.into()),
+            Instruction::FloatMultiplySingle { n, m, destination } =>
+                float_format(0b00011110001, m, 0b000010, n, destination.into()),
+            Instruction::FloatMultiplyDouble { n, m, destination } =>
+                float_format(0b00011110011, m, 0b000010, n, destination.into()),
+            Instruction::FloatDivideSingle { n, m, destination } =>
+                float_format(0b00011110001, m, 0b000110, n, destination.into()),
+            Instruction::FloatDivideDouble { n, m, destination } =>
+                float_format(0b00011110011, m, 0b000110, n, destination.into()),
+            Instruction::FloatCompareSingle { n, m } =>
+                float_format(0b00011110001, m, 0b001000, n, 0),
+            Instruction::FloatCompareDouble { n, m } =>
+                float_format(0b00011110011, m, 0b001000, n, 0),
+            Instruction::StoreSingle { address, offset, data } =>
+                d_format(0b10111100000, offset, address, data),
+            Instruction::LoadSingle { address, offset, data } =>
+                d_format(0b10111100010, offset, address, data),
+            Instruction::StoreDouble { address, offset, data } =>
+                d_format(0b11111100000, offset, address, data),
+            Instruction::LoadDouble { address, offset, data } =>
+                d_format(0b11111100010, offset, address, data),
+            Instruction::CompareBranchZero { address, r } =>
+                cb_format(0b10110100, address, r.into()),
+            Instruction::CompareBranchNotZero { address, r } =>
+                cb_format(0b10110101, address, r.into()),
+            Instruction::ConditionalBranch { address, condition } =>
+                cb_format(0b01010100, address, condition.into()),
+            Instruction::Branch { address } =>
+                b_format(0b000101, address),
+            Instruction::BranchRegister { r } =>
+                r_format(0b11010110000, 0, 0, r, Register::X0),
+            Instruction::BranchLink { address } =>
+                b_format(0b100101, address),
+        }
+    }
+
+    /// Whether the immediate is an offset in instructions from this instruction
+    pub fn is_branch(&self) -> bool {
+        matches!(
+            *self,
+            Instruction::CompareBranchZero { .. } |
+            Instruction::CompareBranchNotZero { .. } |
+            Instruction::ConditionalBranch { .. } |
+            Instruction::Branch { .. } |
+            Instruction::BranchLink { .. }
+        )
+    }
+
+    /// The smallest and largest values the immediate can hold, or `None` if
+    /// there is no immediate
+    pub fn immediate_range(&self) -> Option<(i64, i64)> {
+        match *self {
+            Instruction::AddImmediate { .. } |
+            Instruction::SubtractImmediate { .. } |
+            Instruction::AddImmediateSetFlags { .. } |
+            Instruction::SubtractImmediateSetFlags { .. } |
+            Instruction::AndImmediate { .. } |
+            Instruction::OrImmediate { .. } |
+            Instruction::XorImmediate { .. } |
+            Instruction::AndImmediateSetFlags { .. } => Some((Immediate12::MIN, Immediate12::MAX)),
+            Instruction::Store { .. } |
+            Instruction::Load { .. } |
+            Instruction::StoreByte { .. } |
+            Instruction::LoadByte { .. } |
+            Instruction::StoreWord { .. } |
+            Instruction::LoadSignedWord { .. } |
+            Instruction::StoreHalfword { .. } |
+            Instruction::LoadHalfword { .. } |
+            Instruction::StoreSingle { .. } |
+            Instruction::LoadSingle { .. } |
+            Instruction::StoreDouble { .. } |
+            Instruction::LoadDouble { .. } => Some((Immediate9::MIN, Immediate9::MAX)),
+            Instruction::MoveZero { .. } |
+            Instruction::MoveKeep { .. } => Some((Immediate16::MIN, Immediate16::MAX)),
+            Instruction::Add { .. } |
+            Instruction::Subtract { .. } |
+            Instruction::And { .. } |
+            Instruction::Or { .. } |
+            Instruction::Xor { .. } |
+            Instruction::LogicalShiftRight { .. } |
+            Instruction::LogicalShiftLeft { .. } => Some((Immediate6::MIN, Immediate6::MAX)),
+            Instruction::CompareBranchZero { .. } |
+            Instruction::CompareBranchNotZero { .. } |
+            Instruction::ConditionalBranch { .. } => Some((Immediate19::MIN, Immediate19::MAX)),
+            Instruction::Branch { .. } |
+            Instruction::BranchLink { .. } => Some((Immediate26::MIN, Immediate26::MAX)),
+            _ => None,
+        }
+    }
+
+    /// The instruction with its immediate replaced by `value`, or `None` if
+    /// there is no immediate or the value does not fit
+    pub fn with_immediate(&self, value: i64) -> Option<Instruction> {
+        Some(match *self {
+            Instruction::Add { n, m, destination, .. } =>
+                Instruction::Add { n, m, shift: Immediate6::new(value)?, destination },
+            Instruction::Subtract { n, m, destination, .. } =>
+                Instruction::Subtract { n, m, shift: Immediate6::new(value)?, destination },
+            Instruction::And { n, m, destination, .. } =>
+                Instruction::And { n, m, shift: Immediate6::new(value)?, destination },
+            Instruction::Or { n, m, destination, .. } =>
+                Instruction::Or { n, m, shift: Immediate6::new(value)?, destination },
+            Instruction::Xor { n, m, destination, .. } =>
+                Instruction::Xor { n, m, shift: Immediate6::new(value)?, destination },
+            Instruction::AddImmediate { n, destination, .. } =>
+                Instruction::AddImmediate { n, m: Immediate12::new(value)?, destination },
+            Instruction::SubtractImmediate { n, destination, .. } =>
+                Instruction::SubtractImmediate { n, m: Immediate12::new(value)?, destination },
+            Instruction::AddImmediateSetFlags { n, destination, .. } =>
+                Instruction::AddImmediateSetFlags { n, m: Immediate12::new(value)?, destination },
+            Instruction::SubtractImmediateSetFlags { n, destination, .. } =>
+                Instruction::SubtractImmediateSetFlags { n, m: Immediate12::new(value)?, destination },
+            Instruction::AndImmediate { n, destination, .. } =>
+                Instruction::AndImmediate { n, m: Immediate12::new(value)?, destination },
+            Instruction::OrImmediate { n, destination, .. } =>
+                Instruction::OrImmediate { n, m: Immediate12::new(value)?, destination },
+            Instruction::XorImmediate { n, destination, .. } =>
+                Instruction::XorImmediate { n, m: Immediate12::new(value)?, destination },
+            Instruction::AndImmediateSetFlags { n, destination, .. } =>
+                Instruction::AndImmediateSetFlags { n, m: Immediate12::new(value)?, destination },
+            Instruction::Store { address, data, .. } =>
+                Instruction::Store { address, offset: Immediate9::new(value)?, data },
+            Instruction::Load { address, data, .. } =>
+                Instruction::Load { address, offset: Immediate9::new(value)?, data },
+            Instruction::StoreByte { address, data, .. } =>
+                Instruction::StoreByte { address, offset: Immediate9::new(value)?, data },
+            Instruction::LoadByte { address, data, .. } =>
+                Instruction::LoadByte { address, offset: Immediate9::new(value)?, data },
+            Instruction::StoreWord { address, data, .. } =>
+                Instruction::StoreWord { address, offset: Immediate9::new(value)?, data },
+            Instruction::LoadSignedWord { address, data, .. } =>
+                Instruction::LoadSignedWord { address, offset: Immediate9::new(value)?, data },
+            Instruction::StoreHalfword { address, data, .. } =>
+                Instruction::StoreHalfword { address, offset: Immediate9::new(value)?, data },
+            Instruction::LoadHalfword { address, data, .. } =>
+                Instruction::LoadHalfword { address, offset: Immediate9::new(value)?, data },
+            Instruction::StoreSingle { address, data, .. } =>
+                Instruction::StoreSingle { address, offset: Immediate9::new(value)?, data },
+            Instruction::LoadSingle { address, data, .. } =>
+                Instruction::LoadSingle { address, offset: Immediate9::new(value)?, data },
+            Instruction::StoreDouble { address, data, .. } =>
+                Instruction::StoreDouble { address, offset: Immediate9::new(value)?, data },
+            Instruction::LoadDouble { address, data, .. } =>
+                Instruction::LoadDouble { address, offset: Immediate9::new(value)?, data },
+            Instruction::MoveZero { shift, destination, .. } =>
+                Instruction::MoveZero { immediate: Immediate16::new(value)?, shift, destination },
+            Instruction::MoveKeep { shift, destination, .. } =>
+                Instruction::MoveKeep { immediate: Immediate16::new(value)?, shift, destination },
+            Instruction::LogicalShiftRight { n, destination, .. } =>
+                Instruction::LogicalShiftRight { n, m: Immediate6::new(value)?, destination },
+            Instruction::LogicalShiftLeft { n, destination, .. } =>
+                Instruction::LogicalShiftLeft { n, m: Immediate6::new(value)?, destination },
+            Instruction::CompareBranchZero { r, .. } =>
+                Instruction::CompareBranchZero { address: Immediate19::new(value)?, r },
+            Instruction::CompareBranchNotZero { r, .. } =>
+                Instruction::CompareBranchNotZero { address: Immediate19::new(value)?, r },
+            Instruction::ConditionalBranch { condition, .. } =>
+                Instruction::ConditionalBranch { address: Immediate19::new(value)?, condition },
+            Instruction::Branch { .. } =>
+                Instruction::Branch { address: Immediate26::new(value)? },
+            Instruction::BranchLink { .. } =>
+                Instruction::BranchLink { address: Immediate26::new(value)? },
+            _ => return None,
+        })
+    }
+}
+
+impl From<Instruction> for u32 {
+    fn from(instruction: Instruction) -> u32 {
+        instruction.encode()
+    }
+}
+
 impl From<Instruction> for [Bit; 32] {
     fn from(instruction: Instruction) -> [Bit; 32] {
-        let mut result: [Bit; 32] = [Bit::Zero; 32];
-
-        match instruction {
-            Instruction::Add {
-                m: m,
-                n: n,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[16..21].copy_from_slice(&<[Bit; 5]>::from(m));
-                result[21..32].copy_from_slice(&bit_array![0, 0, 0, 1, 1, 0, 1, 0, 0, 0, 1]);
-            }
-
-            Instruction::Subtract {
-                m: m,
-                n: n,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[16..21].copy_from_slice(&<[Bit; 5]>::from(m));
-                result[21..32].copy_from_slice(&bit_array![0, 0, 0, 1, 1, 0, 1, 0, 0, 1, 1]);
-            }
-
-            Instruction::AddImmediate {
-                n: n,
-                m: i,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[10..22].copy_from_slice(&<[Bit; 12]>::from(i));
-                result[22..32].copy_from_slice(&bit_array![0, 0, 1, 0, 0, 0, 1, 0, 0, 1]);
-            }
-            Instruction::SubtractImmediate {
-                n: n,
-                m: i,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[10..22].copy_from_slice(&<[Bit; 12]>::from(i));
-                result[22..32].copy_from_slice(&bit_array![0, 0, 1, 0, 0, 0, 1, 0, 1, 1]);
-            }
-            Instruction::AddSetFlags {
-                n: n,
-                m: m,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[16..21].copy_from_slice(&<[Bit; 5]>::from(m));
-                result[21..32].copy_from_slice(&bit_array![0, 0, 0, 1, 1, 0, 1, 0, 1, 0, 1]);
-            }
-            Instruction::SubtractSetFlags {
-                n: n,
-                m: m,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[16..21].copy_from_slice(&<[Bit; 5]>::from(m));
-                result[21..32].copy_from_slice(&bit_array![0, 0, 0, 1, 1, 0, 1, 0, 1, 1, 1]);
-            }
-            Instruction::AddImmediateSetFlags {
-                n: n,
-                m: i,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[10..22].copy_from_slice(&<[Bit; 12]>::from(i));
-                result[22..32].copy_from_slice(&bit_array![0, 0, 1, 0, 0, 0, 1, 1, 0, 1]);
-            }
-            Instruction::SubtractImmediateSetFlags {
-                n: n,
-                m: i,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[10..22].copy_from_slice(&<[Bit; 12]>::from(i));
-                result[22..32].copy_from_slice(&bit_array![0, 0, 1, 0, 0, 0, 1, 1, 1, 1]);
-            }
-            Instruction::Store {
-                address: a,
-                offset: o,
-                data: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(a));
-                result[12..21].copy_from_slice(&<[Bit; 9]>::from(o));
-                result[21..32].copy_from_slice(&bit_array![0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1]);
-            }
-            Instruction::Load {
-                address: a,
-                offset: o,
-                data: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(a));
-                result[12..21].copy_from_slice(&<[Bit; 9]>::from(o));
-                result[21..32].copy_from_slice(&bit_array![0, 1, 0, 0, 0, 0, 1, 1, 1, 1, 1]);
-            }
-            Instruction::StoreByte {
-                address: a,
-                offset: o,
-                data: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(a));
-                result[12..21].copy_from_slice(&<[Bit; 9]>::from(o));
-                result[21..32].copy_from_slice(&bit_array![0, 0, 0, 0, 0, 0, 1, 1, 1, 0, 0]);
-            }
-            Instruction::LoadByte {
-                address: a,
-                offset: o,
-                data: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(a));
-                result[12..21].copy_from_slice(&<[Bit; 9]>::from(o));
-                result[21..32].copy_from_slice(&bit_array![0, 1, 0, 0, 0, 0, 1, 1, 1, 0, 0]);
-            }
-            Instruction::MoveZero {
-                immediate: i,
-                shift: s,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..21].copy_from_slice(&<[Bit; 16]>::from(i));
-                result[21..23].copy_from_slice(&<[Bit; 2]>::from(s));
-                result[23..32].copy_from_slice(&bit_array![1, 0, 1, 0, 0, 1, 0, 1, 1]);
-            }
-            Instruction::MoveKeep {
-                immediate: i,
-                shift: s,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..21].copy_from_slice(&<[Bit; 16]>::from(i));
-                result[21..23].copy_from_slice(&<[Bit; 2]>::from(s));
-                result[23..32].copy_from_slice(&bit_array![1, 0, 1, 0, 0, 1, 1, 1, 1]);
-            }
-            Instruction::And {
-                n: n,
-                m: m,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[16..21].copy_from_slice(&<[Bit; 5]>::from(m));
-                result[21..32].copy_from_slice(&bit_array![0, 0, 0, 0, 1, 0, 1, 0, 0, 0, 1]);
-            }
-            Instruction::Or {
-                n: n,
-                m: m,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[16..21].copy_from_slice(&<[Bit; 5]>::from(m));
-                result[21..32].copy_from_slice(&bit_array![0, 0, 0, 0, 1, 0, 1, 0, 1, 0, 1]);
-            }
-            Instruction::Xor {
-                n: n,
-                m: m,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[16..21].copy_from_slice(&<[Bit; 5]>::from(m));
-                result[21..32].copy_from_slice(&bit_array![0, 0, 0, 0, 1, 0, 1, 0, 0, 1, 1]);
-            }
-            Instruction::AndImmediate {
-                n: n,
-                m: i,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[10..22].copy_from_slice(&<[Bit; 12]>::from(i));
-                result[22..32].copy_from_slice(&bit_array![0, 0, 0, 1, 0, 0, 1, 0, 0, 1]);
-            }
-            Instruction::OrImmediate {
-                n: n,
-                m: i,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[10..22].copy_from_slice(&<[Bit; 12]>::from(i));
-                result[22..32].copy_from_slice(&bit_array![0, 0, 0, 1, 0, 0, 1, 1, 0, 1]);
-            }
-            Instruction::XorImmediate {
-                n: n,
-                m: i,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[10..22].copy_from_slice(&<[Bit; 12]>::from(i));
-                result[22..32].copy_from_slice(&bit_array![0, 0, 0, 1, 0, 0, 1, 0, 1, 1]);
-            }
-            Instruction::AndSetFlags {
-                n: n,
-                m: m,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[16..21].copy_from_slice(&<[Bit; 5]>::from(m));
-                result[21..32].copy_from_slice(&bit_array![0, 0, 0, 0, 1, 0, 1, 0, 1, 1, 1]);
-            }
-            Instruction::AndImmediateSetFlags {
-                n: n,
-                m: i,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[10..22].copy_from_slice(&<[Bit; 12]>::from(i));
-                result[22..32].copy_from_slice(&bit_array![0, 0, 0, 1, 0, 0, 1, 1, 1, 1]);
-            }
-            Instruction::LogicalShiftRight {
-                n: n,
-                m: i,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[10..16].copy_from_slice(&<[Bit; 6]>::from(i));
-                result[21..32].copy_from_slice(&bit_array![0, 1, 0, 1, 1, 0, 0, 1, 0, 1, 1]);
-            }
-            Instruction::LogicalShiftLeft {
-                n: n,
-                m: i,
-                destination: d
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(d));
-                result[5..10].copy_from_slice(&<[Bit; 5]>::from(n));
-                result[10..16].copy_from_slice(&<[Bit; 6]>::from(i));
-                result[21..32].copy_from_slice(&bit_array![1, 1, 0, 1, 1, 0, 0, 1, 0, 1, 1]);
-            }
-            Instruction::CompareBranchZero { address: a, r: r } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(r));
-                result[5..24].copy_from_slice(&<[Bit; 19]>::from(a));
-                result[24..32].copy_from_slice(&bit_array![0, 0, 1, 0, 1, 1, 0, 1]);
-            }
-            Instruction::CompareBranchNotZero { address: a, r: r } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(r));
-                result[5..24].copy_from_slice(&<[Bit; 19]>::from(a));
-                result[24..32].copy_from_slice(&bit_array![1, 0, 1, 0, 1, 1, 0, 1]);
-            }
-            Instruction::ConditionalBranch {
-                address: a,
-                condition: c
-            } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(c));
-                result[5..24].copy_from_slice(&<[Bit; 19]>::from(a));
-                result[24..32].copy_from_slice(&bit_array![0, 0, 1, 0, 1, 0, 1, 0]);
-            }
-            Instruction::Branch { address: a } => {
-                result[0..26].copy_from_slice(&<[Bit; 26]>::from(a));
-                result[26..32].copy_from_slice(&bit_array![1, 0, 1, 0, 0, 0]);
-            }
-            Instruction::BranchRegister { r: r } => {
-                result[0..5].copy_from_slice(&<[Bit; 5]>::from(r));
-                result[21..32].copy_from_slice(&bit_array![0, 0, 0, 0, 1, 1, 0, 1, 0, 1, 1]);
-            }
-            Instruction::BranchLink { address: a } => {
-                result[0..26].copy_from_slice(&<[Bit; 26]>::from(a));
-                result[26..32].copy_from_slice(&bit_array![1, 0, 1, 0, 0, 1]);
-            }
-        };
-
-        result
+        bit::bits_from_word(instruction.encode())
     }
 }
 
+#[test]
+fn test_encode() {
+    assert_eq!(
+        Instruction::Store { address: Register::X7, offset: Immediate9(50), data: Register::X23 }.encode(),
+        0xF80320F7
+    );
+    assert_eq!(Instruction::Branch { address: Immediate26(-6) }.encode(), 0x17FFFFFA);
+    assert_eq!(
+        u32::from(Instruction::ConditionalBranch { address: Immediate19(12), condition: Condition::NotEqual }),
+        0x54000181
+    );
+}
+
+#[test]
+fn test_store_display() {
+    assert_eq!(
+        Instruction::Store { address: Register::X7, offset: Immediate9(50), data: Register::X23 }.to_string(),
+        "STUR X23, [X7, #50]"
+    );
+}
+
+#[test]
+fn test_move_zero_display() {
+    assert_eq!(
+        Instruction::MoveZero { immediate: Immediate16(4660), shift: Shift16::Shift16, destination: Register::X1 }.to_string(),
+        "MOVZ X1, 4660, LSL 16"
+    );
+}
+
+#[test]
+fn test_conditional_branch_display() {
+    assert_eq!(
+        Instruction::ConditionalBranch { address: Immediate19(12), condition: Condition::NotEqual }.to_string(),
+        "B.NE 12"
+    );
+}
+
 #[test]
 fn test_add() {
     assert_eq!(
         <[Bit; 32]>::from(Instruction::Add {
             m: Register::X1,
             n: Register::X2,
+            shift: Immediate6(0),
             destination: Register::X3
         }),
         bit_array![
@@ -465,12 +798,21 @@ fn test_add() {
     );
 }
 
+#[test]
+fn test_add_shifted() {
+    let add = Instruction::Add { n: Register::X2, m: Register::X3, shift: Immediate6(4), destination: Register::X1 };
+    assert_eq!(add.encode(), 0x8B031041);
+    assert_eq!(add.to_string(), "ADD X1, X2, X3, LSL 4");
+    assert_eq!(add.with_immediate(64), None);
+}
+
 #[test]
 fn test_subtract() {
     assert_eq!(
         <[Bit; 32]>::from(Instruction::Subtract {
             m: Register::X1,
             n: Register::X2,
+            shift: Immediate6(0),
             destination: Register::X3
         }),
         bit_array![
@@ -666,6 +1008,7 @@ fn test_and() {
         <[Bit; 32]>::from(Instruction::And{
             m: Register::X1,
             n: Register::X2,
+            shift: Immediate6(0),
             destination: Register::X3
         }),
         bit_array![
@@ -681,6 +1024,7 @@ fn test_or() {
         <[Bit; 32]>::from(Instruction::Or{
             m: Register::X1,
             n: Register::X2,
+            shift: Immediate6(0),
             destination: Register::X3
         }),
         bit_array![
@@ -696,6 +1040,7 @@ fn test_xor() {
         <[Bit; 32]>::from(Instruction::Xor{
             m: Register::X1,
             n: Register::X2,
+            shift: Immediate6(0),
             destination: Register::X3
         }),
         bit_array![
@@ -872,7 +1217,7 @@ fn test_branch_register() {
             r: Register::X1,
         }),
         bit_array![
-            1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 1,
+            0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 1,
             0, 1, 1
         ]
     );
@@ -890,3 +1235,150 @@ fn test_branch_link() {
         ]
     );
 }
+
+#[test]
+fn test_store_word() {
+    assert_eq!(
+        Instruction::StoreWord { address: Register::X2, offset: Immediate9(8), data: Register::X1 }.encode(),
+        0xB8008041
+    );
+}
+
+#[test]
+fn test_load_signed_word() {
+    assert_eq!(
+        Instruction::LoadSignedWord { address: Register::X2, offset: Immediate9(-4), data: Register::X1 }.encode(),
+        0xB89FC041
+    );
+}
+
+#[test]
+fn test_store_halfword() {
+    assert_eq!(
+        Instruction::StoreHalfword { address: Register::X2, offset: Immediate9(2), data: Register::X1 }.encode(),
+        0x78002041
+    );
+}
+
+#[test]
+fn test_load_halfword() {
+    assert_eq!(
+        Instruction::LoadHalfword { address: Register::X2, offset: Immediate9(0), data: Register::X1 }.encode(),
+        0x78400041
+    );
+}
+
+#[test]
+fn test_load_exclusive() {
+    assert_eq!(
+        Instruction::LoadExclusive { address: Register::X1, data: Register::X0 }.encode(),
+        0xC85F7C20
+    );
+}
+
+#[test]
+fn test_store_exclusive() {
+    assert_eq!(
+        Instruction::StoreExclusive { address: Register::X11, data: Register::X9, status: Register::X10 }.encode(),
+        0xC80A7D69
+    );
+}
+
+#[test]
+fn test_multiply() {
+    assert_eq!(
+        Instruction::Multiply { n: Register::X2, m: Register::X1, destination: Register::X3 }.encode(),
+        0x9B017C43
+    );
+}
+
+#[test]
+fn test_signed_multiply_high() {
+    assert_eq!(
+        Instruction::SignedMultiplyHigh { n: Register::X2, m: Register::X1, destination: Register::X3 }.encode(),
+        0x9B417C43
+    );
+}
+
+#[test]
+fn test_unsigned_multiply_high() {
+    assert_eq!(
+        Instruction::UnsignedMultiplyHigh { n: Register::X2, m: Register::X1, destination: Register::X3 }.encode(),
+        0x9BC17C43
+    );
+}
+
+#[test]
+fn test_signed_divide() {
+    // The reference card gives SDIV opcode 0x4D6 and shamt 0x02
+    assert_eq!(
+        Instruction::SignedDivide { n: Register::X2, m: Register::X1, destination: Register::X3 }.encode(),
+        0x4D6 << 21 | 1 << 16 | 0x02 << 10 | 2 << 5 | 3
+    );
+}
+
+#[test]
+fn test_unsigned_divide() {
+    // The reference card gives UDIV opcode 0x4D6 and shamt 0x03
+    assert_eq!(
+        Instruction::UnsignedDivide { n: Register::X2, m: Register::X1, destination: Register::X3 }.encode(),
+        0x4D6 << 21 | 1 << 16 | 0x03 << 10 | 2 << 5 | 3
+    );
+}
+
+#[test]
+fn test_float_arithmetic() {
+    let (n, m, destination) = (FloatRegister::F2, FloatRegister::F1, FloatRegister::F3);
+    assert_eq!(Instruction::FloatAddSingle { n, m, destination }.encode(), 0x1E212843);
+    assert_eq!(Instruction::FloatAddDouble { n, m, destination }.encode(), 0x1E612843);
+    assert_eq!(Instruction::FloatSubtractSingle { n, m, destination }.encode(), 0x1E213843);
+    assert_eq!(Instruction::FloatSubtractDouble { n, m, destination }.encode(), 0x1E613843);
+    assert_eq!(Instruction::FloatMultiplySingle { n, m, destination }.encode(), 0x1E210843);
+    assert_eq!(Instruction::FloatMultiplyDouble { n, m, destination }.encode(), 0x1E610843);
+    assert_eq!(Instruction::FloatDivideSingle { n, m, destination }.encode(), 0x1E211843);
+    assert_eq!(Instruction::FloatDivideDouble { n, m, destination }.encode(), 0x1E611843);
+}
+
+#[test]
+fn test_float_compare() {
+    assert_eq!(Instruction::FloatCompareSingle { n: FloatRegister::F2, m: FloatRegister::F1 }.encode(), 0x1E212040);
+    assert_eq!(Instruction::FloatCompareDouble { n: FloatRegister::F2, m: FloatRegister::F1 }.encode(), 0x1E612040);
+}
+
+#[test]
+fn test_float_load_store() {
+    let (address, data) = (Register::X2, FloatRegister::F1);
+    assert_eq!(Instruction::StoreSingle { address, offset: Immediate9(8), data }.encode(), 0xBC008041);
+    assert_eq!(Instruction::LoadSingle { address, offset: Immediate9(8), data }.encode(), 0xBC408041);
+    assert_eq!(Instruction::StoreDouble { address, offset: Immediate9(-8), data }.encode(), 0xFC1F8041);
+    assert_eq!(Instruction::LoadDouble { address, offset: Immediate9(-8), data }.encode(), 0xFC5F8041);
+}
+
+#[test]
+fn test_float_display() {
+    let (n, m, destination) = (FloatRegister::F2, FloatRegister::F1, FloatRegister::F3);
+    assert_eq!(Instruction::FloatAddDouble { n, m, destination }.to_string(), "FADDD D3, D2, D1");
+    assert_eq!(Instruction::FloatCompareSingle { n, m }.to_string(), "FCMPS S2, S1");
+    assert_eq!(
+        Instruction::LoadSingle { address: Register::X4, offset: Immediate9(4), data: destination }.to_string(),
+        "LDURS S3, [X4, #4]"
+    );
+}
+
+#[test]
+fn test_with_immediate() {
+    let add = Instruction::AddImmediate { n: Register::X1, m: Immediate12(0), destination: Register::X2 };
+    assert_eq!(
+        add.with_immediate(100),
+        Some(Instruction::AddImmediate { n: Register::X1, m: Immediate12(100), destination: Register::X2 })
+    );
+    assert_eq!(add.with_immediate(4096), None);
+    assert_eq!(Instruction::BranchRegister { r: Register::X30 }.with_immediate(0), None);
+}
+
+#[test]
+fn test_immediate_range() {
+    assert_eq!(Instruction::Branch { address: Immediate26(0) }.immediate_range(), Some((-(1 << 25), (1 << 25) - 1)));
+    assert!(Instruction::Branch { address: Immediate26(0) }.is_branch());
+    assert_eq!(Instruction::BranchRegister { r: Register::X30 }.immediate_range(), None);
+}
@@ -11,30 +11,143 @@ pub mod shift16;
 pub mod condition;
 pub mod immediate;
 pub mod generator;
+pub mod diagnostic;
+pub mod disassembler;
+pub mod expression;
 
-use bit::Bit;
+use diagnostic::Diagnostic;
+use instruction::Instruction;
 use parser::AsmLine;
+use parser::Section;
 
-#[no_mangle]
-pub fn parse_to_rom(assembly: &str) -> String {
-    let parsed_instructions = parser::parse_lines(assembly);
+/// Assemble a program into its instructions, leaving out any data, or the
+/// problems that stopped it assembling
+pub fn assemble(assembly: &str) -> Result<Vec<Instruction>, Vec<Diagnostic>> {
+    let mut instructions: Vec<Instruction> = Vec::new();
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+    for asm_line in parser::parse_lines(assembly) {
+        match asm_line.into_statement() {
+            AsmLine::Instruction(i) => instructions.push(i),
+            AsmLine::Instructions(i) => instructions.extend(i),
+            AsmLine::Error(diagnostic) => diagnostics.push(diagnostic),
+            _ => {}
+        }
+    }
+
+    if diagnostics.is_empty() {
+        Ok(instructions)
+    } else {
+        Err(diagnostics)
+    }
+}
+
+/// The memory images of an assembled program
+#[derive(Debug, PartialEq)]
+pub struct Image {
+    /// The words of the `.text` section, for the instruction memory
+    pub text: Vec<u32>,
+    /// The words of the `.data` section, for the data memory
+    pub data: Vec<u32>,
+    /// The comment on the source line each `.text` word starts, if it has one
+    pub text_comments: Vec<Option<String>>,
+    /// The comment on the source line each `.data` word starts, if it has one
+    pub data_comments: Vec<Option<String>>,
+}
+
+/// Pack bytes into words, least significant byte first, padding the last word with zeros
+fn pack_words(bytes: &[u8]) -> Vec<u32> {
+    bytes.chunks(4).map(|chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        u32::from_le_bytes(word)
+    }).collect()
+}
+
+/// Give each of `words` words the comments of the lines starting at byte
+/// offsets in it, joined with `; ` when more than one line starts in a word
+fn word_comments(comments: &[(usize, &str)], words: usize) -> Vec<Option<String>> {
+    let mut word_comments: Vec<Option<String>> = vec![None; words];
 
-    let mut error_lines: Vec<u32> = Vec::new();
-    let mut instruction_lines: Vec<[Bit; 32]> = Vec::new();
+    for &(offset, comment) in comments {
+        match word_comments[offset / 4] {
+            Some(ref mut joined) => {
+                joined.push_str("; ");
+                joined.push_str(comment);
+            }
+            ref mut empty => *empty = Some(comment.to_owned()),
+        }
+    }
+
+    word_comments
+}
 
-    for (index, asm_line) in parsed_instructions.into_iter().enumerate() {
-        match asm_line {
-            AsmLine::Instruction(i) => instruction_lines.push(<[Bit; 32]>::from(i)),
-            AsmLine::Error => error_lines.push(index as u32),
+/// Assemble a program into the memory images of its sections, or the
+/// problems that stopped it assembling
+///
+/// Instructions and data are laid out in order in their section, with data
+/// packed into words least significant byte first. The comment on a line
+/// that makes instructions or data goes with the word its first byte is in.
+pub fn assemble_image(assembly: &str) -> Result<Image, Vec<Diagnostic>> {
+    let mut text: Vec<u8> = Vec::new();
+    let mut data: Vec<u8> = Vec::new();
+    let mut text_comments: Vec<(usize, &str)> = Vec::new();
+    let mut data_comments: Vec<(usize, &str)> = Vec::new();
+    let mut section = Section::Text;
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+    for asm_line in parser::parse_lines(assembly) {
+        let (bytes, comments) = match section {
+            Section::Text => (&mut text, &mut text_comments),
+            Section::Data => (&mut data, &mut data_comments),
+        };
+
+        let start = bytes.len();
+        let comment = asm_line.comment();
+
+        match asm_line.into_statement() {
+            AsmLine::Instruction(i) => bytes.extend_from_slice(&i.encode().to_le_bytes()),
+            AsmLine::Instructions(instructions) => for i in instructions {
+                bytes.extend_from_slice(&i.encode().to_le_bytes());
+            },
+            AsmLine::Data(d) => bytes.extend_from_slice(&d),
+            AsmLine::Section(next) => section = next,
+            AsmLine::Error(diagnostic) => diagnostics.push(diagnostic),
+            _ => {}
+        }
+
+        match comment {
+            Some(comment) if bytes.len() > start => comments.push((start, comment)),
             _ => {}
         }
     }
 
-    if error_lines.is_empty() {
-        generator::generate_case_rom(instruction_lines)
+    if diagnostics.is_empty() {
+        let text = pack_words(&text);
+        let data = pack_words(&data);
+        Ok(Image {
+            text_comments: word_comments(&text_comments, text.len()),
+            data_comments: word_comments(&data_comments, data.len()),
+            text,
+            data,
+        })
     } else {
-        error_lines.into_iter().fold("".to_owned(), |mut errors, line| {
-            errors.push_str(&format!("Error on line {}", line));
+        Err(diagnostics)
+    }
+}
+
+/// Assemble a program into the words of its `.text` section, or the problems
+/// that stopped it assembling
+pub fn assemble_words(assembly: &str) -> Result<Vec<u32>, Vec<Diagnostic>> {
+    assemble_image(assembly).map(|image| image.text)
+}
+
+#[no_mangle]
+pub fn parse_to_rom(assembly: &str) -> String {
+    match assemble_words(assembly) {
+        Ok(words) => generator::generate_case_rom_words(&words),
+        Err(diagnostics) => diagnostics.into_iter().fold("".to_owned(), |mut errors, diagnostic| {
+            errors.push_str(&format!("{}\n", diagnostic));
             errors
         })
     }
@@ -65,3 +178,79 @@ end
 "
     )
 }
+
+#[test]
+fn test_assemble_errors() {
+    assert_eq!(
+        assemble("ADD X1, X2, X3\nADDX X1, X2, X3\nB nowhere"),
+        Err(vec![
+            Diagnostic::error(2, 1, "ADDX", "unknown mnemonic `ADDX`".to_owned()),
+            Diagnostic::error(3, 3, "nowhere", "undefined symbol `nowhere`".to_owned()),
+        ])
+    );
+}
+
+#[test]
+fn test_parse_to_rom_errors() {
+    assert_eq!(
+        parse_to_rom("ADD X1, X2, X3\nADDX X1, X2, X3\nB nowhere"),
+        "2:1: error: unknown mnemonic `ADDX`\n3:3: error: undefined symbol `nowhere`\n"
+    );
+}
+
+#[test]
+fn test_assemble_words_pseudo_instructions() {
+    assert_eq!(
+        assemble_words("LDI X0, 0x10000\nNOP"),
+        Ok(vec![0xD2800000, 0xF2A00020, 0x8B1F03FF])
+    );
+}
+
+#[test]
+fn test_assemble_words_data() {
+    assert_eq!(
+        assemble_words("B start\ntable: .byte 1, 2, 3\n.align 2\nstart: ADD X1, X2, X3\n.ascii \"Hi\""),
+        Ok(vec![0x14000002, 0x00030201, 0x8B030041, 0x00006948])
+    );
+}
+
+#[test]
+fn test_assemble_image() {
+    assert_eq!(
+        assemble_image("\
+.data
+table: .dword 5
+count: .byte 3
+.text
+    LDUR X1, [XZR, table]
+    LDURB X2, [XZR, count]
+    MOVZ X3, count
+"),
+        Ok(Image {
+            text: vec![0xF84003E1, 0x384083E2, 0xD2800103],
+            data: vec![5, 0, 3],
+            text_comments: vec![None; 3],
+            data_comments: vec![None; 3],
+        })
+    );
+}
+
+#[test]
+fn test_assemble_image_comments() {
+    let image = assemble_image("\
+// the program
+start: ADD X1, X2, X3 // add
+    LDI X4, 0x10000 ; load
+    B start
+.data
+.byte 1 // first
+.byte 2 @ second
+.word 3
+").unwrap();
+
+    assert_eq!(
+        image.text_comments,
+        vec![Some("add".to_owned()), Some("load".to_owned()), None, None]
+    );
+    assert_eq!(image.data_comments, vec![Some("first; second".to_owned()), None]);
+}
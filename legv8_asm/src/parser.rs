@@ -1,13 +1,27 @@
-#![allow(unused_imports)]
+#![allow(unused_imports, unused_doc_comments)]
 use nom::digit;
+use nom::rest;
+use nom::space;
+use nom::space0;
+use nom::space1;
 use nom::line_ending;
 use nom::IResult;
+use nom::ErrorKind;
 use nom::types::CompleteStr;
 
+use std::collections::HashMap;
+
+use diagnostic::Diagnostic;
+
 use register::Register;
+use register::FloatRegister;
 use shift16::Shift16;
 use condition::Condition;
 use instruction::Instruction;
+use expression::BinaryOperator;
+use expression::Expression;
+use expression::ExpressionError;
+use expression::UnaryOperator;
 
 use immediate::Immediate6;
 use immediate::Immediate9;
@@ -19,396 +33,2752 @@ use immediate::Immediate26;
 #[derive(PartialEq, Debug)]
 pub enum AsmLine<'a> {
     Instruction(Instruction),
+    /// An instruction whose immediate uses labels or constants, or does not
+    /// fit, turned into an `Instruction` by `parse_lines`
+    Symbolic(Symbolic<'a>),
+    /// `LDI Xd, value` with a value using labels or constants, turned into
+    /// `Instructions` by `parse_lines`
+    LoadImmediate(Register, Expression<'a>, &'a str),
+    /// The instructions a pseudo-instruction like `LDI` expands to
+    Instructions(Vec<Instruction>),
+    /// A constant from `.equ NAME, value` or `.set NAME, value`
+    Constant(&'a str, Expression<'a>),
+    /// A label definition in the form `loop:` and the rest of the line after it
+    Label(&'a str, Box<AsmLine<'a>>),
+    /// A line ending in a comment, like `ADD X1, X2, X3 // add`
+    Commented(Box<AsmLine<'a>>, &'a str),
+    /// Bytes of data from a directive like `.word 1, 2`
+    Data(Vec<u8>),
+    /// Values from `.byte`, `.word` or `.dword` that use labels or constants,
+    /// each `usize` bytes long, turned into `Data` by `parse_lines`
+    Values(usize, Vec<(Expression<'a>, &'a str)>),
+    /// `.space` with a size that uses constants, and its fill byte, turned
+    /// into `Data` by `parse_lines`
+    Space(Expression<'a>, &'a str, u8),
+    /// Padding up to a multiple of `2^n` bytes from `.align n`, turned into
+    /// `Data` by `parse_lines`
+    Align(u32),
+    /// The start of a section from `.text` or `.data`
+    Section(Section),
+    /// A line that is only a comment
     Comment(&'a str),
     Blank,
-    Error,
+    Error(Diagnostic),
+}
+
+impl<'a> AsmLine<'a> {
+    /// The labels defined at the start of the line
+    pub fn labels(&self) -> Vec<&'a str> {
+        let mut labels = Vec::new();
+        let mut line = self;
+        while let AsmLine::Label(label, ref rest) = *line {
+            labels.push(label);
+            line = rest;
+        }
+        labels
+    }
+
+    /// The text of the comment on the line, if there is one
+    pub fn comment(&self) -> Option<&'a str> {
+        match *self {
+            AsmLine::Label(_, ref rest) => rest.comment(),
+            AsmLine::Commented(_, comment) | AsmLine::Comment(comment) => Some(comment),
+            _ => None,
+        }
+    }
+
+    /// The line without any label definitions or trailing comment
+    pub fn into_statement(self) -> AsmLine<'a> {
+        match self {
+            AsmLine::Label(_, rest) | AsmLine::Commented(rest, _) => rest.into_statement(),
+            line => line,
+        }
+    }
+
+    /// Whether the line is one or more instructions
+    fn is_instruction(&self) -> bool {
+        matches!(
+            *self,
+            AsmLine::Instruction(_) | AsmLine::Symbolic(_) | AsmLine::LoadImmediate(..) | AsmLine::Instructions(_)
+        )
+    }
+
+    /// Name the instruction on the line with the mnemonic it was written with
+    fn with_mnemonic(self, mnemonic: &'a str) -> AsmLine<'a> {
+        match self {
+            AsmLine::Symbolic(symbolic) => AsmLine::Symbolic(Symbolic { mnemonic, ..symbolic }),
+            line => line,
+        }
+    }
+
+    fn statement_mut(&mut self) -> &mut AsmLine<'a> {
+        match *self {
+            AsmLine::Label(_, ref mut rest) | AsmLine::Commented(ref mut rest, _) => rest.statement_mut(),
+            ref mut line => line,
+        }
+    }
 }
 
-/// Parse a register in the form `X23` to an `instruction::Register`
+/// An instruction with a placeholder immediate, and the expression to fill it in with
+#[derive(PartialEq, Debug)]
+pub struct Symbolic<'a> {
+    pub instruction: Instruction,
+    /// The mnemonic the instruction was written with, which may be a
+    /// pseudo-instruction like `CMPI`
+    pub mnemonic: &'a str,
+    /// The value of the immediate, or for branches the address to branch to
+    pub expression: Expression<'a>,
+    /// The text of the expression, for pointing at in errors
+    pub text: &'a str,
+}
+
+/// A part of the program that goes in its own memory
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Section {
+    /// Instructions, and any data mixed in with them, for the instruction memory
+    Text,
+    /// Data for the data memory
+    Data,
+}
+
+/// Parse a register in the form `X23`, or one of the aliases `SP`, `FP`,
+/// `LR`, `IP0` and `IP1`, in any case to an `instruction::Register`
 named!(
     parse_register<CompleteStr, Register>,
-    do_parse!(
-        tag!("X") >>
-        register: switch!(alt!(digit | tag!("ZR")),
-            CompleteStr("0")  => value!(Register::X0)  |
-            CompleteStr("1")  => value!(Register::X1)  |
-            CompleteStr("2")  => value!(Register::X2)  |
-            CompleteStr("3")  => value!(Register::X3)  |
-            CompleteStr("4")  => value!(Register::X4)  |
-            CompleteStr("5")  => value!(Register::X5)  |
-            CompleteStr("6")  => value!(Register::X6)  |
-            CompleteStr("7")  => value!(Register::X7)  |
-            CompleteStr("8")  => value!(Register::X8)  |
-            CompleteStr("9")  => value!(Register::X9)  |
-            CompleteStr("10") => value!(Register::X10) |
-            CompleteStr("11") => value!(Register::X11) |
-            CompleteStr("12") => value!(Register::X12) |
-            CompleteStr("13") => value!(Register::X13) |
-            CompleteStr("14") => value!(Register::X14) |
-            CompleteStr("15") => value!(Register::X15) |
-            CompleteStr("16") => value!(Register::X16) |
-            CompleteStr("17") => value!(Register::X17) |
-            CompleteStr("18") => value!(Register::X18) |
-            CompleteStr("19") => value!(Register::X19) |
-            CompleteStr("20") => value!(Register::X20) |
-            CompleteStr("21") => value!(Register::X21) |
-            CompleteStr("22") => value!(Register::X22) |
-            CompleteStr("23") => value!(Register::X23) |
-            CompleteStr("24") => value!(Register::X24) |
-            CompleteStr("25") => value!(Register::X25) |
-            CompleteStr("26") => value!(Register::X26) |
-            CompleteStr("27") => value!(Register::X27) |
-            CompleteStr("28") => value!(Register::X28) |
-            CompleteStr("29") => value!(Register::X29) |
-            CompleteStr("30") => value!(Register::X30) |
-            CompleteStr("ZR") => value!(Register::XZR)
-        ) >>
-        (register)
+    alt!(
+        do_parse!(
+            tag_no_case!("X") >>
+            register: switch!(alt!(digit | value!(CompleteStr("ZR"), tag_no_case!("ZR"))),
+                CompleteStr("0")  => value!(Register::X0)  |
+                CompleteStr("1")  => value!(Register::X1)  |
+                CompleteStr("2")  => value!(Register::X2)  |
+                CompleteStr("3")  => value!(Register::X3)  |
+                CompleteStr("4")  => value!(Register::X4)  |
+                CompleteStr("5")  => value!(Register::X5)  |
+                CompleteStr("6")  => value!(Register::X6)  |
+                CompleteStr("7")  => value!(Register::X7)  |
+                CompleteStr("8")  => value!(Register::X8)  |
+                CompleteStr("9")  => value!(Register::X9)  |
+                CompleteStr("10") => value!(Register::X10) |
+                CompleteStr("11") => value!(Register::X11) |
+                CompleteStr("12") => value!(Register::X12) |
+                CompleteStr("13") => value!(Register::X13) |
+                CompleteStr("14") => value!(Register::X14) |
+                CompleteStr("15") => value!(Register::X15) |
+                CompleteStr("16") => value!(Register::X16) |
+                CompleteStr("17") => value!(Register::X17) |
+                CompleteStr("18") => value!(Register::X18) |
+                CompleteStr("19") => value!(Register::X19) |
+                CompleteStr("20") => value!(Register::X20) |
+                CompleteStr("21") => value!(Register::X21) |
+                CompleteStr("22") => value!(Register::X22) |
+                CompleteStr("23") => value!(Register::X23) |
+                CompleteStr("24") => value!(Register::X24) |
+                CompleteStr("25") => value!(Register::X25) |
+                CompleteStr("26") => value!(Register::X26) |
+                CompleteStr("27") => value!(Register::X27) |
+                CompleteStr("28") => value!(Register::X28) |
+                CompleteStr("29") => value!(Register::X29) |
+                CompleteStr("30") => value!(Register::X30) |
+                CompleteStr("ZR") => value!(Register::XZR)
+            ) >>
+            (register)
+        ) |
+        value!(Register::X16, tag_no_case!("IP0")) |
+        value!(Register::X17, tag_no_case!("IP1")) |
+        value!(Register::X28, tag_no_case!("SP")) |
+        value!(Register::X29, tag_no_case!("FP")) |
+        value!(Register::X30, tag_no_case!("LR"))
+    )
+);
+
+/// Parse a floating point register written with `prefix` and its number,
+/// like `S12` or `D3`
+fn parse_float_register<'a>(input: CompleteStr<'a>, prefix: &str) -> IResult<CompleteStr<'a>, FloatRegister> {
+    preceded!(
+        input,
+        tag_no_case!(prefix),
+        map_opt!(digit, |digits: CompleteStr| if digits.0.len() > 1 && digits.0.starts_with('0') {
+            None
+        } else {
+            digits.0.parse().ok().and_then(FloatRegister::from_u8)
+        })
     )
+}
+
+/// Parse a single precision floating point register in the form `S12`
+named!(
+    parse_single_register<CompleteStr, FloatRegister>,
+    call!(parse_float_register, "S")
 );
 
-/// Parse a 6 bit immidiate value
+/// Parse a double precision floating point register in the form `D12`
 named!(
-    parse_immediate_6<CompleteStr, Immediate6>,
-    do_parse!(
-        s: opt!(tag!("-")) >>
-        i: map_res!(digit, |d: CompleteStr| d.0.parse::<i8>()) >>
-        (Immediate6(if s.is_some() { -i } else { i }))
+    parse_double_register<CompleteStr, FloatRegister>,
+    call!(parse_float_register, "D")
+);
+
+/// Parse digits in the given radix, allowing `_` between them
+///
+/// Numbers up to 64 bits are accepted, with ones above `i64::MAX` wrapping
+/// around to negative so masks like `0xFFFF_FFFF_FFFF_FFFF` can be written.
+fn parse_digits(input: CompleteStr, radix: u32) -> IResult<CompleteStr, i64> {
+    map_res!(
+        input,
+        verify!(
+            take_while1!(|c: char| c.is_digit(radix) || c == '_'),
+            |digits: CompleteStr| digits.0.starts_with(|c: char| c.is_digit(radix))
+        ),
+        |digits: CompleteStr| u64::from_str_radix(&digits.0.replace('_', ""), radix).map(|number| number as i64)
     )
+}
+
+/// Parse an escape sequence in a character or string literal, like `\n`
+named!(
+    parse_escape<CompleteStr, char>,
+    preceded!(tag!("\\"), alt!(
+        value!('\n', tag!("n")) |
+        value!('\t', tag!("t")) |
+        value!('\r', tag!("r")) |
+        value!('\0', tag!("0")) |
+        value!('\\', tag!("\\")) |
+        value!('\'', tag!("'")) |
+        value!('"', tag!("\""))
+    ))
 );
 
-/// Parse a 9 bit immidiate value
+/// Parse a character literal in the form `'A'` or `'\n'` to its ASCII value
 named!(
-    parse_immediate_9<CompleteStr, Immediate9>,
-    do_parse!(
-        s: opt!(tag!("-")) >>
-        i: map_res!(digit, |d: CompleteStr| d.0.parse::<i16>()) >>
-        (Immediate9(if s.is_some() { -i } else { i }))
+    parse_character<CompleteStr, i64>,
+    map!(
+        delimited!(tag!("'"), alt!(parse_escape | none_of!("\\'")), tag!("'")),
+        |c| c as i64
     )
 );
 
-/// Parse a 12 bit immidiate value
+/// Parse a string literal in the form `"Hello\n"`
 named!(
-    parse_immediate_12<CompleteStr, Immediate12>,
-    do_parse!(
-        s: opt!(tag!("-")) >>
-        i: map_res!(digit, |d: CompleteStr| d.0.parse::<i16>()) >>
-        (Immediate12(if s.is_some() { -i } else { i }))
+    parse_string<CompleteStr, String>,
+    map!(
+        delimited!(tag!("\""), many0!(alt!(parse_escape | none_of!("\\\""))), tag!("\"")),
+        |chars: Vec<char>| chars.into_iter().collect()
     )
 );
 
-/// Parse a 16 bit immidiate value
+/// Parse an optionally negative number, optionally starting with `#`
+///
+/// Numbers can be decimal, hexadecimal with `0x`, binary with `0b`, octal
+/// with `0o` or a character literal like `'A'`, and can have `_` between
+/// digits.
 named!(
-    parse_immediate_16<CompleteStr, Immediate16>,
+    parse_number<CompleteStr, i64>,
     do_parse!(
+        opt!(tag!("#")) >>
         s: opt!(tag!("-")) >>
-        i: map_res!(digit, |d: CompleteStr| d.0.parse::<i16>()) >>
-        (Immediate16(if s.is_some() { -i } else { i }))
+        i: alt!(
+            preceded!(alt!(tag!("0x") | tag!("0X")), call!(parse_digits, 16)) |
+            preceded!(alt!(tag!("0b") | tag!("0B")), call!(parse_digits, 2)) |
+            preceded!(alt!(tag!("0o") | tag!("0O")), call!(parse_digits, 8)) |
+            parse_character |
+            call!(parse_digits, 10)
+        ) >>
+        (if s.is_some() { i.wrapping_neg() } else { i })
     )
 );
 
-/// Parse a 19 bit immidiate value
+/// Parse a name used in an expression, which can not be a register
 named!(
-    parse_immediate_19<CompleteStr, Immediate19>,
-    do_parse!(
-        s: opt!(tag!("-")) >>
-        i: map_res!(digit, |d: CompleteStr| d.0.parse::<i32>()) >>
-        (Immediate19(if s.is_some() { -i } else { i }))
+    parse_symbol<CompleteStr, CompleteStr>,
+    verify!(parse_label, |label: CompleteStr| {
+        !matches!(parse_register(label), Ok((rest, _)) if rest.0.is_empty()) &&
+            !matches!(parse_single_register(label), Ok((rest, _)) if rest.0.is_empty()) &&
+            !matches!(parse_double_register(label), Ok((rest, _)) if rest.0.is_empty())
+    })
+);
+
+/// Parse a number, a name, an expression in parentheses, or one of those
+/// after `-` or `~`
+named!(
+    parse_unary<CompleteStr, Expression>,
+    alt!(
+        parse_number => { Expression::Number } |
+        do_parse!(
+            tag!("-") >> space0 >>
+            operand: parse_unary >>
+            (Expression::Unary(UnaryOperator::Negate, Box::new(operand)))
+        ) |
+        do_parse!(
+            tag!("~") >> space0 >>
+            operand: parse_unary >>
+            (Expression::Unary(UnaryOperator::Not, Box::new(operand)))
+        ) |
+        delimited!(pair!(tag!("("), space0), parse_or, pair!(space0, tag!(")"))) |
+        do_parse!(
+            symbol: parse_symbol >>
+            (Expression::Symbol(symbol.0))
+        )
     )
 );
 
-/// Parse a 26 bit immidiate value
+/// Parse operands joined by any of `operators`, which all have the same
+/// precedence, grouping from the left
+fn parse_binary<'a>(
+    input: CompleteStr<'a>,
+    operators: &[(&str, BinaryOperator)],
+    operand: fn(CompleteStr<'a>) -> IResult<CompleteStr<'a>, Expression<'a>>,
+) -> IResult<CompleteStr<'a>, Expression<'a>> {
+    let is_space = |c: char| c == ' ' || c == '\t';
+    let (mut input, mut expression) = operand(input)?;
+
+    loop {
+        let after = input.0.trim_start_matches(is_space);
+
+        // `//` starts a comment rather than being a division
+        let operator = operators.iter()
+            .find(|&&(symbol, _)| after.starts_with(symbol) && !after.starts_with("//"));
+
+        let (symbol, operator) = match operator {
+            Some(&operator) => operator,
+            None => return Ok((input, expression)),
+        };
+
+        let (rest, right) = operand(CompleteStr(after[symbol.len()..].trim_start_matches(is_space)))?;
+        expression = Expression::Binary(operator, Box::new(expression), Box::new(right));
+        input = rest;
+    }
+}
+
+fn parse_product(input: CompleteStr) -> IResult<CompleteStr, Expression> {
+    parse_binary(input, &[("*", BinaryOperator::Multiply), ("/", BinaryOperator::Divide)], parse_unary)
+}
+
+fn parse_sum(input: CompleteStr) -> IResult<CompleteStr, Expression> {
+    parse_binary(input, &[("+", BinaryOperator::Add), ("-", BinaryOperator::Subtract)], parse_product)
+}
+
+fn parse_shifted(input: CompleteStr) -> IResult<CompleteStr, Expression> {
+    parse_binary(input, &[("<<", BinaryOperator::ShiftLeft), (">>", BinaryOperator::ShiftRight)], parse_sum)
+}
+
+fn parse_and(input: CompleteStr) -> IResult<CompleteStr, Expression> {
+    parse_binary(input, &[("&", BinaryOperator::And)], parse_shifted)
+}
+
+fn parse_or(input: CompleteStr) -> IResult<CompleteStr, Expression> {
+    parse_binary(input, &[("|", BinaryOperator::Or)], parse_and)
+}
+
+/// Parse an expression like `(end - start) / 8`, optionally starting with `#`
+///
+/// From lowest to highest precedence, the operators are `|`, `&`, `<<` and
+/// `>>`, `+` and `-`, `*` and `/`, and the unary `-` and `~`.
+named!(
+    parse_expression<CompleteStr, Expression>,
+    preceded!(opt!(tag!("#")), parse_or)
+);
+
+/// Parse an expression, also returning its text
+fn parse_expression_text<'a>(input: CompleteStr<'a>) -> IResult<CompleteStr<'a>, (Expression<'a>, &'a str)> {
+    let (rest, expression) = parse_expression(input)?;
+    let text = input.0[..input.0.len() - rest.0.len()].trim_end();
+    Ok((rest, (expression, text)))
+}
+
+/// The value of an expression that does not use any labels or constants
+fn constant_value(expression: &Expression) -> Option<i64> {
+    if expression.is_constant() {
+        expression.evaluate(&|name| Err(ExpressionError::Undefined(name))).ok()
+    } else {
+        None
+    }
+}
+
+/// Parse an expression that does not use any labels or constants to its value
+named!(
+    parse_constant<CompleteStr, i64>,
+    map_opt!(parse_expression, |expression| constant_value(&expression))
+);
+
+/// An immediate operand of an instruction
+enum Value<'a, T> {
+    /// An immediate that fits, worked out while parsing
+    Known(T),
+    /// An expression using labels or constants, or that does not fit, to work
+    /// out in `parse_lines`
+    Symbolic(Expression<'a>, &'a str),
+}
+
+impl<'a, T: Default> Value<'a, T> {
+    /// Build the line for the instruction `build` makes from the immediate
+    fn build<F: FnOnce(T) -> Instruction>(self, build: F) -> AsmLine<'a> {
+        match self {
+            Value::Known(immediate) => AsmLine::Instruction(build(immediate)),
+            Value::Symbolic(expression, text) => AsmLine::Symbolic(Symbolic {
+                instruction: build(T::default()),
+                mnemonic: "",
+                expression,
+                text,
+            }),
+        }
+    }
+}
+
+/// Parse an immediate with `parse`, or an expression to work out later
+fn parse_value<'a, T>(
+    input: CompleteStr<'a>,
+    parse: fn(CompleteStr<'a>) -> IResult<CompleteStr<'a>, T>,
+) -> IResult<CompleteStr<'a>, Value<'a, T>> {
+    if let Ok((rest, immediate)) = parse(input) {
+        return Ok((rest, Value::Known(immediate)));
+    }
+
+    let (rest, (expression, text)) = parse_expression_text(input)?;
+    Ok((rest, Value::Symbolic(expression, text)))
+}
+
+/// Parse a 6 bit immidiate value, failing if the number does not fit
+named!(
+    parse_immediate_6<CompleteStr, Immediate6>,
+    map_opt!(parse_constant, Immediate6::new)
+);
+
+/// Parse a 9 bit immidiate value, failing if the number does not fit
+named!(
+    parse_immediate_9<CompleteStr, Immediate9>,
+    map_opt!(parse_constant, Immediate9::new)
+);
+
+/// Parse a 12 bit immidiate value, failing if the number does not fit
+named!(
+    parse_immediate_12<CompleteStr, Immediate12>,
+    map_opt!(parse_constant, Immediate12::new)
+);
+
+/// Parse a 16 bit immidiate value, failing if the number does not fit
+named!(
+    parse_immediate_16<CompleteStr, Immediate16>,
+    map_opt!(parse_constant, Immediate16::new)
+);
+
+/// Parse a 19 bit immidiate value, failing if the number does not fit
+named!(
+    parse_immediate_19<CompleteStr, Immediate19>,
+    map_opt!(parse_constant, Immediate19::new)
+);
+
+/// Parse a 26 bit immidiate value, failing if the number does not fit
 named!(
     parse_immediate_26<CompleteStr, Immediate26>,
-    do_parse!(
-        s: opt!(tag!("-")) >>
-        i: map_res!(digit, |d: CompleteStr| d.0.parse::<i32>()) >>
-        (Immediate26(if s.is_some() { -i } else { i }))
-    )
+    map_opt!(parse_constant, Immediate26::new)
 );
 
+/// Parse the offset of an exclusive load or store, which can only be 0
+named!(
+    parse_zero_offset<CompleteStr, i64>,
+    verify!(parse_constant, |offset| offset == 0)
+);
+
+/// Parse how far to shift the last register of an R-format instruction, in
+/// the form `, LSL 4`, or no shift if there is none
+fn parse_register_shift<'a>(input: CompleteStr<'a>) -> IResult<CompleteStr<'a>, Value<'a, Immediate6>> {
+    alt!(
+        input,
+        complete!(preceded!(
+            tuple!(ws!(tag!(",")), tag_no_case!("LSL"), space),
+            call!(parse_value, parse_immediate_6)
+        )) |
+        value!(Value::Known(Immediate6(0)))
+    )
+}
+
 /// Parse a shift amount in the form `LSL 16` to an `instruction::Shift16`
 named!(
     parse_shift<CompleteStr, Shift16>,
     do_parse!(
-        ws!(tag!("LSL")) >>
-        shift: switch!(digit,
-            CompleteStr("0") => value!(Shift16::Shift0) |
-            CompleteStr("16") => value!(Shift16::Shift16) |
-            CompleteStr("32") => value!(Shift16::Shift32) |
-            CompleteStr("48") => value!(Shift16::Shift48)
+        ws!(tag_no_case!("LSL")) >>
+        shift: switch!(parse_number,
+            0 => value!(Shift16::Shift0) |
+            16 => value!(Shift16::Shift16) |
+            32 => value!(Shift16::Shift32) |
+            48 => value!(Shift16::Shift48)
         ) >>
         (shift)
     )
 );
 
-/// Parse a condition into an `instruction::Condition`
+/// Parse a condition in any case into an `instruction::Condition`
 named!(
     parse_condition<CompleteStr, Condition>,
-    do_parse!(
-        condition: switch!(take!(2),
-            CompleteStr("EQ") => value!(Condition::Equal) |
-            CompleteStr("NE") => value!(Condition::NotEqual) |
-            CompleteStr("HS") => value!(Condition::HigherSame) |
-            CompleteStr("LO") => value!(Condition::Lower) |
-            CompleteStr("MI") => value!(Condition::Minus) |
-            CompleteStr("PL") => value!(Condition::PositiveZero) |
-            CompleteStr("VS") => value!(Condition::SignedOverflow) |
-            CompleteStr("VC") => value!(Condition::NoSignedOverflow) |
-            CompleteStr("HI") => value!(Condition::Higher) |
-            CompleteStr("LS") => value!(Condition::LowerSame) |
-            CompleteStr("GE") => value!(Condition::GreaterThanEqual) |
-            CompleteStr("LT") => value!(Condition::LessThan) |
-            CompleteStr("GT") => value!(Condition::GreaterThan) |
-            CompleteStr("LE") => value!(Condition::LessThanEqual) |
-            CompleteStr("AL") => value!(Condition::Always) |
-            CompleteStr("NV") => value!(Condition::Reserved)
-        ) >>
-        (condition)
+    alt!(
+        value!(Condition::Equal, tag_no_case!("EQ")) |
+        value!(Condition::NotEqual, tag_no_case!("NE")) |
+        value!(Condition::HigherSame, tag_no_case!("HS")) |
+        value!(Condition::Lower, tag_no_case!("LO")) |
+        value!(Condition::Minus, tag_no_case!("MI")) |
+        value!(Condition::PositiveZero, tag_no_case!("PL")) |
+        value!(Condition::SignedOverflow, tag_no_case!("VS")) |
+        value!(Condition::NoSignedOverflow, tag_no_case!("VC")) |
+        value!(Condition::Higher, tag_no_case!("HI")) |
+        value!(Condition::LowerSame, tag_no_case!("LS")) |
+        value!(Condition::GreaterThanEqual, tag_no_case!("GE")) |
+        value!(Condition::LessThan, tag_no_case!("LT")) |
+        value!(Condition::GreaterThan, tag_no_case!("GT")) |
+        value!(Condition::LessThanEqual, tag_no_case!("LE")) |
+        value!(Condition::Always, tag_no_case!("AL")) |
+        value!(Condition::Reserved, tag_no_case!("NV"))
     )
 );
 
-/// Parse an instruction and its arguments into an `instruction::Instruction`
+/// The mnemonics of the instructions `parse_statement` understands
+const MNEMONICS: &[&str] = &[
+    "ADD", "SUB", "ADDI", "SUBI", "ADDS", "SUBS", "ADDIS", "SUBIS",
+    "STUR", "LDUR", "STURB", "LDURB", "STURW", "LDURSW", "STURH", "LDURH", "LDXR", "STXR", "MOVZ", "MOVK",
+    "AND", "ORR", "EOR", "ANDI", "ORRI", "EORI", "ANDS", "ANDIS", "LSR", "LSL",
+    "CBZ", "CBNZ", "B", "BR", "BL",
+    "MUL", "SMULH", "UMULH", "SDIV", "UDIV",
+    "MOV", "CMP", "CMPI", "NOP", "NEG", "LDI",
+    "FADDS", "FADDD", "FSUBS", "FSUBD", "FMULS", "FMULD", "FDIVS", "FDIVD", "FCMPS", "FCMPD",
+    "LDURS", "LDURD", "STURS", "STURD",
+];
+
+/// Parse a mnemonic in any case and the spaces after it into its upper case
+/// form, keeping the `.` of `B.cond` so conditional branches can be told
+/// apart from branches
 named!(
-    parse_instruction<CompleteStr, Instruction>,
+    parse_mnemonic<CompleteStr, CompleteStr>,
+    alt!(
+        value!(CompleteStr("B."), tag_no_case!("B.")) |
+        map_opt!(
+            terminated!(
+                take_while1!(|c: char| c.is_ascii_alphanumeric()),
+                alt!(space1 | tag!(".") | eof!())
+            ),
+            |mnemonic: CompleteStr| MNEMONICS.iter()
+            .find(|known| known.eq_ignore_ascii_case(mnemonic.0))
+            .map(|&known| CompleteStr(known))
+        )
+    )
+);
+
+/// Parse an instruction and its arguments into an `AsmLine::Instruction`, or
+/// an `AsmLine::Symbolic` if an immediate uses labels or constants
+///
+/// The pseudo-instructions `MOV`, `CMP`, `CMPI`, `NOP` and `NEG` become the
+/// instruction they stand for, and `LDI` becomes `AsmLine::LoadImmediate`.
+named!(
+    parse_statement<CompleteStr, AsmLine>,
     do_parse!(
-        instruction: switch!(take_until_either_and_consume!(" ."),
+        mnemonic: peek!(take_while1!(|c: char| c == '.' || c.is_ascii_alphanumeric())) >>
+        instruction: switch!(parse_mnemonic,
             CompleteStr("ADD") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
                 m: parse_register >>
-                (Instruction::Add { n: n, m: m, destination: d })
+                s: parse_register_shift >>
+                (s.build(|shift| Instruction::Add { n, m, shift, destination: d }))
             ) |
             CompleteStr("SUB") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
                 m: parse_register >>
-                (Instruction::Subtract { n: n, m: m, destination: d })
+                s: parse_register_shift >>
+                (s.build(|shift| Instruction::Subtract { n, m, shift, destination: d }))
             ) |
             CompleteStr("ADDI") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
-                i: parse_immediate_12 >>
-                (Instruction::AddImmediate { n: n, m: i, destination: d })
+                i: call!(parse_value, parse_immediate_12) >>
+                (i.build(|i| Instruction::AddImmediate { n, m: i, destination: d }))
             ) |
             CompleteStr("SUBI") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
-                i: parse_immediate_12 >>
-                (Instruction::SubtractImmediate { n: n, m: i, destination: d })
+                i: call!(parse_value, parse_immediate_12) >>
+                (i.build(|i| Instruction::SubtractImmediate { n, m: i, destination: d }))
             ) |
             CompleteStr("ADDS") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
                 m: parse_register >>
-                (Instruction::AddSetFlags { n: n, m: m, destination: d })
+                (AsmLine::Instruction(Instruction::AddSetFlags { n, m, destination: d }))
             ) |
             CompleteStr("SUBS") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
                 m: parse_register >>
-                (Instruction::SubtractSetFlags { n: n, m: m, destination: d })
+                (AsmLine::Instruction(Instruction::SubtractSetFlags { n, m, destination: d }))
             ) |
             CompleteStr("ADDIS") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
-                i: parse_immediate_12 >>
-                (Instruction::AddImmediateSetFlags { n: n, m: i, destination: d })
+                i: call!(parse_value, parse_immediate_12) >>
+                (i.build(|i| Instruction::AddImmediateSetFlags { n, m: i, destination: d }))
             ) |
             CompleteStr("SUBIS") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
-                i: parse_immediate_12 >>
-                (Instruction::SubtractImmediateSetFlags { n: n, m: i, destination: d })
+                i: call!(parse_value, parse_immediate_12) >>
+                (i.build(|i| Instruction::SubtractImmediateSetFlags { n, m: i, destination: d }))
             ) |
             CompleteStr("STUR") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >> ws!(tag!("[")) >>
                 a: parse_register >> ws!(tag!(",")) >>
-                o: parse_immediate_9 >> ws!(tag!("]")) >>
-                (Instruction::Store { address: a, offset: o, data: d })
+                o: call!(parse_value, parse_immediate_9) >> ws!(tag!("]")) >>
+                (o.build(|o| Instruction::Store { address: a, offset: o, data: d }))
             ) |
             CompleteStr("LDUR") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >> ws!(tag!("[")) >>
                 a: parse_register >> ws!(tag!(",")) >>
-                o: parse_immediate_9 >> ws!(tag!("]")) >>
-                (Instruction::Load { address: a, offset: o, data: d })
+                o: call!(parse_value, parse_immediate_9) >> ws!(tag!("]")) >>
+                (o.build(|o| Instruction::Load { address: a, offset: o, data: d }))
             ) |
             CompleteStr("STURB") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >> ws!(tag!("[")) >>
                 a: parse_register >> ws!(tag!(",")) >>
-                o: parse_immediate_9 >> ws!(tag!("]")) >>
-                (Instruction::StoreByte{ address: a, offset: o, data: d })
+                o: call!(parse_value, parse_immediate_9) >> ws!(tag!("]")) >>
+                (o.build(|o| Instruction::StoreByte { address: a, offset: o, data: d }))
             ) |
             CompleteStr("LDURB") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >> ws!(tag!("[")) >>
                 a: parse_register >> ws!(tag!(",")) >>
-                o: parse_immediate_9 >> ws!(tag!("]")) >>
-                (Instruction::LoadByte { address: a, offset: o, data: d })
+                o: call!(parse_value, parse_immediate_9) >> ws!(tag!("]")) >>
+                (o.build(|o| Instruction::LoadByte { address: a, offset: o, data: d }))
+            ) |
+            CompleteStr("STURW") => do_parse!(
+                d: parse_register >> ws!(tag!(",")) >> ws!(tag!("[")) >>
+                a: parse_register >> ws!(tag!(",")) >>
+                o: call!(parse_value, parse_immediate_9) >> ws!(tag!("]")) >>
+                (o.build(|o| Instruction::StoreWord { address: a, offset: o, data: d }))
+            ) |
+            CompleteStr("LDURSW") => do_parse!(
+                d: parse_register >> ws!(tag!(",")) >> ws!(tag!("[")) >>
+                a: parse_register >> ws!(tag!(",")) >>
+                o: call!(parse_value, parse_immediate_9) >> ws!(tag!("]")) >>
+                (o.build(|o| Instruction::LoadSignedWord { address: a, offset: o, data: d }))
+            ) |
+            CompleteStr("STURH") => do_parse!(
+                d: parse_register >> ws!(tag!(",")) >> ws!(tag!("[")) >>
+                a: parse_register >> ws!(tag!(",")) >>
+                o: call!(parse_value, parse_immediate_9) >> ws!(tag!("]")) >>
+                (o.build(|o| Instruction::StoreHalfword { address: a, offset: o, data: d }))
+            ) |
+            CompleteStr("LDURH") => do_parse!(
+                d: parse_register >> ws!(tag!(",")) >> ws!(tag!("[")) >>
+                a: parse_register >> ws!(tag!(",")) >>
+                o: call!(parse_value, parse_immediate_9) >> ws!(tag!("]")) >>
+                (o.build(|o| Instruction::LoadHalfword { address: a, offset: o, data: d }))
+            ) |
+            CompleteStr("LDXR") => do_parse!(
+                d: parse_register >> ws!(tag!(",")) >> ws!(tag!("[")) >>
+                a: parse_register >> ws!(tag!(",")) >>
+                parse_zero_offset >> ws!(tag!("]")) >>
+                (AsmLine::Instruction(Instruction::LoadExclusive { address: a, data: d }))
+            ) |
+            CompleteStr("STXR") => do_parse!(
+                d: parse_register >> ws!(tag!(",")) >>
+                s: parse_register >> ws!(tag!(",")) >> ws!(tag!("[")) >>
+                a: parse_register >> ws!(tag!(",")) >>
+                parse_zero_offset >> ws!(tag!("]")) >>
+                (AsmLine::Instruction(Instruction::StoreExclusive { address: a, data: d, status: s }))
+            ) |
+            CompleteStr("FADDS") => do_parse!(
+                d: parse_single_register >> ws!(tag!(",")) >>
+                n: parse_single_register >> ws!(tag!(",")) >>
+                m: parse_single_register >>
+                (AsmLine::Instruction(Instruction::FloatAddSingle { n, m, destination: d }))
+            ) |
+            CompleteStr("FADDD") => do_parse!(
+                d: parse_double_register >> ws!(tag!(",")) >>
+                n: parse_double_register >> ws!(tag!(",")) >>
+                m: parse_double_register >>
+                (AsmLine::Instruction(Instruction::FloatAddDouble { n, m, destination: d }))
+            ) |
+            CompleteStr("FSUBS") => do_parse!(
+                d: parse_single_register >> ws!(tag!(",")) >>
+                n: parse_single_register >> ws!(tag!(",")) >>
+                m: parse_single_register >>
+                (AsmLine::Instruction(Instruction::FloatSubtractSingle { n, m, destination: d }))
+            ) |
+            CompleteStr("FSUBD") => do_parse!(
+                d: parse_double_register >> ws!(tag!(",")) >>
+                n: parse_double_register >> ws!(tag!(",")) >>
+                m: parse_double_register >>
+                (AsmLine::Instruction(Instruction::FloatSubtractDouble { n, m, destination: d }))
+            ) |
+            CompleteStr("FMULS") => do_parse!(
+                d: parse_single_register >> ws!(tag!(",")) >>
+                n: parse_single_register >> ws!(tag!(",")) >>
+                m: parse_single_register >>
+                (AsmLine::Instruction(Instruction::FloatMultiplySingle { n, m, destination: d }))
+            ) |
+            CompleteStr("FMULD") => do_parse!(
+                d: parse_double_register >> ws!(tag!(",")) >>
+                n: parse_double_register >> ws!(tag!(",")) >>
+                m: parse_double_register >>
+                (AsmLine::Instruction(Instruction::FloatMultiplyDouble { n, m, destination: d }))
+            ) |
+            CompleteStr("FDIVS") => do_parse!(
+                d: parse_single_register >> ws!(tag!(",")) >>
+                n: parse_single_register >> ws!(tag!(",")) >>
+                m: parse_single_register >>
+                (AsmLine::Instruction(Instruction::FloatDivideSingle { n, m, destination: d }))
+            ) |
+            CompleteStr("FDIVD") => do_parse!(
+                d: parse_double_register >> ws!(tag!(",")) >>
+                n: parse_double_register >> ws!(tag!(",")) >>
+                m: parse_double_register >>
+                (AsmLine::Instruction(Instruction::FloatDivideDouble { n, m, destination: d }))
+            ) |
+            CompleteStr("FCMPS") => do_parse!(
+                n: parse_single_register >> ws!(tag!(",")) >>
+                m: parse_single_register >>
+                (AsmLine::Instruction(Instruction::FloatCompareSingle { n, m }))
+            ) |
+            CompleteStr("FCMPD") => do_parse!(
+                n: parse_double_register >> ws!(tag!(",")) >>
+                m: parse_double_register >>
+                (AsmLine::Instruction(Instruction::FloatCompareDouble { n, m }))
+            ) |
+            CompleteStr("STURS") => do_parse!(
+                d: parse_single_register >> ws!(tag!(",")) >> ws!(tag!("[")) >>
+                a: parse_register >> ws!(tag!(",")) >>
+                o: call!(parse_value, parse_immediate_9) >> ws!(tag!("]")) >>
+                (o.build(|o| Instruction::StoreSingle { address: a, offset: o, data: d }))
+            ) |
+            CompleteStr("LDURS") => do_parse!(
+                d: parse_single_register >> ws!(tag!(",")) >> ws!(tag!("[")) >>
+                a: parse_register >> ws!(tag!(",")) >>
+                o: call!(parse_value, parse_immediate_9) >> ws!(tag!("]")) >>
+                (o.build(|o| Instruction::LoadSingle { address: a, offset: o, data: d }))
+            ) |
+            CompleteStr("STURD") => do_parse!(
+                d: parse_double_register >> ws!(tag!(",")) >> ws!(tag!("[")) >>
+                a: parse_register >> ws!(tag!(",")) >>
+                o: call!(parse_value, parse_immediate_9) >> ws!(tag!("]")) >>
+                (o.build(|o| Instruction::StoreDouble { address: a, offset: o, data: d }))
+            ) |
+            CompleteStr("LDURD") => do_parse!(
+                d: parse_double_register >> ws!(tag!(",")) >> ws!(tag!("[")) >>
+                a: parse_register >> ws!(tag!(",")) >>
+                o: call!(parse_value, parse_immediate_9) >> ws!(tag!("]")) >>
+                (o.build(|o| Instruction::LoadDouble { address: a, offset: o, data: d }))
             ) |
             CompleteStr("MOVZ") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
-                i: parse_immediate_16 >>
+                i: call!(parse_value, parse_immediate_16) >>
                 s: alt!(complete!(preceded!(ws!(tag!(",")), parse_shift)) | value!(Shift16::Shift0)) >>
-                (Instruction::MoveZero { immediate: i, shift: s, destination: d })
+                (i.build(|i| Instruction::MoveZero { immediate: i, shift: s, destination: d }))
             ) |
             CompleteStr("MOVK") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
-                i: parse_immediate_16 >>
+                i: call!(parse_value, parse_immediate_16) >>
                 s: alt!(complete!(preceded!(ws!(tag!(",")), parse_shift)) | value!(Shift16::Shift0)) >>
-                (Instruction::MoveKeep { immediate: i, shift: s, destination: d })
+                (i.build(|i| Instruction::MoveKeep { immediate: i, shift: s, destination: d }))
             ) |
             CompleteStr("AND") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
                 m: parse_register >>
-                (Instruction::And{ n: n, m: m, destination: d })
+                s: parse_register_shift >>
+                (s.build(|shift| Instruction::And { n, m, shift, destination: d }))
             ) |
             CompleteStr("ORR") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
                 m: parse_register >>
-                (Instruction::Or{ n: n, m: m, destination: d })
+                s: parse_register_shift >>
+                (s.build(|shift| Instruction::Or { n, m, shift, destination: d }))
             ) |
             CompleteStr("EOR") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
                 m: parse_register >>
-                (Instruction::Xor{ n: n, m: m, destination: d })
+                s: parse_register_shift >>
+                (s.build(|shift| Instruction::Xor { n, m, shift, destination: d }))
             ) |
             CompleteStr("ANDI") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
-                i: parse_immediate_12 >>
-                (Instruction::AndImmediate { n: n, m: i, destination: d })
+                i: call!(parse_value, parse_immediate_12) >>
+                (i.build(|i| Instruction::AndImmediate { n, m: i, destination: d }))
             ) |
             CompleteStr("ORRI") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
-                i: parse_immediate_12 >>
-                (Instruction::OrImmediate { n: n, m: i, destination: d })
+                i: call!(parse_value, parse_immediate_12) >>
+                (i.build(|i| Instruction::OrImmediate { n, m: i, destination: d }))
             ) |
             CompleteStr("EORI") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
-                i: parse_immediate_12 >>
-                (Instruction::XorImmediate { n: n, m: i, destination: d })
+                i: call!(parse_value, parse_immediate_12) >>
+                (i.build(|i| Instruction::XorImmediate { n, m: i, destination: d }))
             ) |
             CompleteStr("ANDS") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
                 m: parse_register >>
-                (Instruction::AndSetFlags { n: n, m: m, destination: d })
+                (AsmLine::Instruction(Instruction::AndSetFlags { n, m, destination: d }))
             ) |
             CompleteStr("ANDIS") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
-                i: parse_immediate_12 >>
-                (Instruction::AndImmediateSetFlags { n: n, m: i, destination: d })
+                i: call!(parse_value, parse_immediate_12) >>
+                (i.build(|i| Instruction::AndImmediateSetFlags { n, m: i, destination: d }))
             ) |
             CompleteStr("LSR") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
-                i: parse_immediate_6 >>
-                (Instruction::LogicalShiftRight { n: n, m: i, destination: d })
+                i: call!(parse_value, parse_immediate_6) >>
+                (i.build(|i| Instruction::LogicalShiftRight { n, m: i, destination: d }))
             ) |
             CompleteStr("LSL") => do_parse!(
                 d: parse_register >> ws!(tag!(",")) >>
                 n: parse_register >> ws!(tag!(",")) >>
-                i: parse_immediate_6 >>
-                (Instruction::LogicalShiftLeft { n: n, m: i, destination: d })
+                i: call!(parse_value, parse_immediate_6) >>
+                (i.build(|i| Instruction::LogicalShiftLeft { n, m: i, destination: d }))
             ) |
             CompleteStr("CBZ") => do_parse!(
                 r: parse_register >> ws!(tag!(",")) >>
-                a: parse_immediate_19 >>
-                (Instruction::CompareBranchZero { address: a, r: r })
+                a: call!(parse_value, parse_immediate_19) >>
+                (a.build(|a| Instruction::CompareBranchZero { address: a, r }))
             ) |
             CompleteStr("CBNZ") => do_parse!(
                 r: parse_register >> ws!(tag!(",")) >>
-                a: parse_immediate_19 >>
-                (Instruction::CompareBranchNotZero { address: a, r: r })
+                a: call!(parse_value, parse_immediate_19) >>
+                (a.build(|a| Instruction::CompareBranchNotZero { address: a, r }))
             ) |
-            CompleteStr("B") => alt!(
-                do_parse!(
-                    c: ws!(parse_condition) >>
-                    a: ws!(parse_immediate_19) >>
-                    (Instruction::ConditionalBranch { address: a, condition: c })
-                ) |
-                do_parse!(
-                    a: ws!(parse_immediate_26) >>
-                    (Instruction::Branch { address: a })
-                )
+            CompleteStr("B.") => do_parse!(
+                c: parse_condition >> space1 >>
+                a: call!(parse_value, parse_immediate_19) >>
+                (a.build(|a| Instruction::ConditionalBranch { address: a, condition: c }))
+            ) |
+            CompleteStr("B") => do_parse!(
+                a: call!(parse_value, parse_immediate_26) >>
+                (a.build(|a| Instruction::Branch { address: a }))
             ) |
             CompleteStr("BR") => do_parse!(
                 r: parse_register >>
-                (Instruction::BranchRegister { r: r })
+                (AsmLine::Instruction(Instruction::BranchRegister { r }))
             ) |
             CompleteStr("BL") => do_parse!(
-                a: parse_immediate_26 >>
-                (Instruction::BranchLink { address: a })
+                a: call!(parse_value, parse_immediate_26) >>
+                (a.build(|a| Instruction::BranchLink { address: a }))
+            ) |
+            CompleteStr("MUL") => do_parse!(
+                d: parse_register >> ws!(tag!(",")) >>
+                n: parse_register >> ws!(tag!(",")) >>
+                m: parse_register >>
+                (AsmLine::Instruction(Instruction::Multiply { n, m, destination: d }))
+            ) |
+            CompleteStr("SMULH") => do_parse!(
+                d: parse_register >> ws!(tag!(",")) >>
+                n: parse_register >> ws!(tag!(",")) >>
+                m: parse_register >>
+                (AsmLine::Instruction(Instruction::SignedMultiplyHigh { n, m, destination: d }))
+            ) |
+            CompleteStr("UMULH") => do_parse!(
+                d: parse_register >> ws!(tag!(",")) >>
+                n: parse_register >> ws!(tag!(",")) >>
+                m: parse_register >>
+                (AsmLine::Instruction(Instruction::UnsignedMultiplyHigh { n, m, destination: d }))
+            ) |
+            CompleteStr("SDIV") => do_parse!(
+                d: parse_register >> ws!(tag!(",")) >>
+                n: parse_register >> ws!(tag!(",")) >>
+                m: parse_register >>
+                (AsmLine::Instruction(Instruction::SignedDivide { n, m, destination: d }))
+            ) |
+            CompleteStr("UDIV") => do_parse!(
+                d: parse_register >> ws!(tag!(",")) >>
+                n: parse_register >> ws!(tag!(",")) >>
+                m: parse_register >>
+                (AsmLine::Instruction(Instruction::UnsignedDivide { n, m, destination: d }))
+            ) |
+            CompleteStr("MOV") => do_parse!(
+                d: parse_register >> ws!(tag!(",")) >>
+                m: parse_register >>
+                (AsmLine::Instruction(Instruction::Or { n: Register::XZR, m, shift: Immediate6(0), destination: d }))
+            ) |
+            CompleteStr("CMP") => do_parse!(
+                n: parse_register >> ws!(tag!(",")) >>
+                m: parse_register >>
+                (AsmLine::Instruction(Instruction::SubtractSetFlags { n, m, destination: Register::XZR }))
+            ) |
+            CompleteStr("CMPI") => do_parse!(
+                n: parse_register >> ws!(tag!(",")) >>
+                i: call!(parse_value, parse_immediate_12) >>
+                (i.build(|i| Instruction::SubtractImmediateSetFlags { n, m: i, destination: Register::XZR }))
+            ) |
+            CompleteStr("NOP") => value!(AsmLine::Instruction(Instruction::Add {
+                n: Register::XZR,
+                m: Register::XZR,
+                shift: Immediate6(0),
+                destination: Register::XZR
+            })) |
+            CompleteStr("NEG") => do_parse!(
+                d: parse_register >> ws!(tag!(",")) >>
+                m: parse_register >>
+                (AsmLine::Instruction(Instruction::Subtract { n: Register::XZR, m, shift: Immediate6(0), destination: d }))
+            ) |
+            CompleteStr("LDI") => do_parse!(
+                d: parse_register >> ws!(tag!(",")) >>
+                value: parse_expression_text >>
+                (AsmLine::LoadImmediate(d, value.0, value.1))
             )
         ) >>
-        (instruction)
+        (instruction.with_mnemonic(mnemonic.0))
+    )
+);
+
+/// Parse an instruction whose immediates are all numbers into an `instruction::Instruction`
+#[cfg(test)]
+named!(
+    parse_instruction<CompleteStr, Instruction>,
+    map_opt!(parse_statement, |line| match line {
+        AsmLine::Instruction(instruction) => Some(instruction),
+        _ => None,
+    })
+);
+
+/// Parse a label name in the form `loop_2`
+named!(
+    parse_label<CompleteStr, CompleteStr>,
+    recognize!(pair!(
+        take_while1!(|c: char| c.is_ascii_alphabetic() || c == '_'),
+        take_while!(|c: char| c.is_ascii_alphanumeric() || c == '_')
+    ))
+);
+
+/// The most bytes `.space` can reserve
+const MAX_SPACE: i64 = 1 << 20;
+
+/// The largest `n` in `.align n`
+const MAX_ALIGN: i64 = 12;
+
+/// The smallest and largest values that fit in `size` bytes, signed or unsigned
+fn data_range(size: usize) -> (i64, i64) {
+    if size >= 8 {
+        (i64::MIN, i64::MAX)
+    } else {
+        (-(1 << (size * 8 - 1)), (1 << (size * 8)) - 1)
+    }
+}
+
+/// Parse a list of values that each fit in `size` bytes into their little
+/// endian bytes, or into `AsmLine::Values` if any use labels or constants
+fn parse_data_values(input: CompleteStr, size: usize) -> IResult<CompleteStr, AsmLine> {
+    let (rest, values) = separated_nonempty_list!(input, ws!(tag!(",")), parse_expression_text)?;
+    let (min, max) = data_range(size);
+
+    let constants: Vec<Option<i64>> = values.iter().map(|(value, _)| constant_value(value)).collect();
+    if constants.iter().any(|value| value.is_some_and(|value| !(min..=max).contains(&value))) {
+        return Err(nom::Err::Error(error_position!(input, ErrorKind::Verify)));
+    }
+
+    if constants.iter().all(Option::is_some) {
+        let bytes = constants.iter().flatten().flat_map(|value| value.to_le_bytes()[..size].to_vec()).collect();
+        Ok((rest, AsmLine::Data(bytes)))
+    } else {
+        Ok((rest, AsmLine::Values(size, values)))
+    }
+}
+
+/// Parse the size and optional fill byte of `.space`
+fn parse_space(input: CompleteStr) -> IResult<CompleteStr, AsmLine> {
+    let (rest, (size, text)) = parse_expression_text(input)?;
+    let (rest, fill) = opt!(
+        rest,
+        preceded!(ws!(tag!(",")), verify!(parse_constant, |fill| (-128..=255).contains(&fill)))
+    )?;
+    let fill = fill.unwrap_or(0) as u8;
+
+    match constant_value(&size) {
+        Some(size) if (0..=MAX_SPACE).contains(&size) => Ok((rest, AsmLine::Data(vec![fill; size as usize]))),
+        Some(_) => Err(nom::Err::Error(error_position!(input, ErrorKind::Verify))),
+        None => Ok((rest, AsmLine::Space(size, text, fill))),
+    }
+}
+
+/// Parse the name and value of `.equ` or `.set`
+named!(
+    parse_constant_definition<CompleteStr, AsmLine>,
+    do_parse!(
+        space0 >>
+        name: parse_symbol >> ws!(tag!(",")) >>
+        value: parse_expression >>
+        (AsmLine::Constant(name.0, value))
+    )
+);
+
+/// Parse a directive like `.word 1, 2` or `.equ SIZE, 8`
+named!(
+    parse_directive<CompleteStr, AsmLine>,
+    switch!(take_while1!(|c: char| c == '.' || c.is_ascii_alphanumeric()),
+        CompleteStr(".byte") => preceded!(space0, call!(parse_data_values, 1)) |
+        CompleteStr(".word") => preceded!(space0, call!(parse_data_values, 4)) |
+        CompleteStr(".dword") => preceded!(space0, call!(parse_data_values, 8)) |
+        CompleteStr(".ascii") => do_parse!(
+            space0 >>
+            string: parse_string >>
+            (AsmLine::Data(string.into_bytes()))
+        ) |
+        CompleteStr(".space") => preceded!(space0, parse_space) |
+        CompleteStr(".text") => value!(AsmLine::Section(Section::Text)) |
+        CompleteStr(".data") => value!(AsmLine::Section(Section::Data)) |
+        CompleteStr(".align") => do_parse!(
+            space0 >>
+            alignment: verify!(parse_constant, |alignment| (0..=MAX_ALIGN).contains(&alignment)) >>
+            (AsmLine::Align(alignment as u32))
+        ) |
+        CompleteStr(".equ") => call!(parse_constant_definition) |
+        CompleteStr(".set") => call!(parse_constant_definition)
+    )
+);
+
+/// Parse a comment starting with `//`, `;` or `@` to the end of the line
+named!(
+    parse_comment<CompleteStr, CompleteStr>,
+    do_parse!(
+        alt!(tag!("//") | tag!(";") | tag!("@")) >>
+        text: rest >>
+        (CompleteStr(text.0.trim()))
+    )
+);
+
+/// Parse a label definition in the form `loop:`, which can not be named
+/// after a register
+named!(
+    parse_label_definition<CompleteStr, CompleteStr>,
+    ws!(terminated!(parse_symbol, tag!(":")))
+);
+
+named!(
+    parse_line<CompleteStr, AsmLine>,
+    alt!(
+        do_parse!(
+            label: parse_label_definition >>
+            line: parse_line >>
+            (AsmLine::Label(label.0, Box::new(line)))
+        ) |
+        do_parse!(
+            space0 >>
+            statement: alt!(
+                parse_statement |
+                parse_directive
+            ) >>
+            space0 >>
+            comment: opt!(parse_comment) >>
+            eof!() >>
+            (match comment {
+                Some(comment) => AsmLine::Commented(Box::new(statement), comment.0),
+                None => statement,
+            })
+        ) |
+        do_parse!(
+            space0 >>
+            comment: parse_comment >>
+            (AsmLine::Comment(comment.0))
+        ) |
+        exact!(ws!(tag!(""))) => { |_| AsmLine::Blank }
+        // the closure takes the result as arguNone ment if the parser is successful
     )
 );
 
-named!(
-    parse_line<CompleteStr, AsmLine>,
-    alt!(
-        parse_instruction => { |i| AsmLine::Instruction(i) } |
-        exact!(ws!(tag!(""))) => { |_| AsmLine::Blank }
-        // the closure takes the result as arguNone ment if the parser is successful
-    )
-);
+/// Something an instruction expects in its operands
+#[derive(Copy, Clone)]
+enum Operand {
+    Register,
+    SingleRegister,
+    DoubleRegister,
+    /// An immediate between the smallest and largest values it can hold
+    Immediate(i64, i64),
+    Target(i64, i64),
+    /// An immediate between the smallest and largest values it can hold, or
+    /// a label to use the address of
+    Address(i64, i64),
+    Shift,
+    /// `LSL` and how far to shift a register, between the smallest and
+    /// largest amounts
+    ShiftAmount(i64, i64),
+    String,
+    /// The name of a constant
+    Symbol,
+    Comma,
+    OpenBracket,
+    CloseBracket,
+}
+
+impl Operand {
+    /// The smallest and largest values the operand can take, if it is a number
+    fn range(self) -> Option<(i64, i64)> {
+        match self {
+            Operand::Immediate(min, max) | Operand::Target(min, max) | Operand::Address(min, max) |
+            Operand::ShiftAmount(min, max) => Some((min, max)),
+            _ => None,
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Operand::Register => "register",
+            Operand::SingleRegister => "`S` register",
+            Operand::DoubleRegister => "`D` register",
+            Operand::Immediate(..) => "immediate",
+            Operand::Target(..) => "label or offset",
+            Operand::Address(..) => "immediate or label",
+            Operand::Shift => "`LSL 0`, `LSL 16`, `LSL 32` or `LSL 48`",
+            Operand::ShiftAmount(..) => "`LSL` and a shift amount",
+            Operand::String => "string",
+            Operand::Symbol => "name",
+            Operand::Comma => "`,`",
+            Operand::OpenBracket => "`[`",
+            Operand::CloseBracket => "`]`",
+        }
+    }
+
+    /// Parse the operand from the start of `input`, returning the text it took up
+    fn parse(self, input: CompleteStr) -> IResult<CompleteStr, CompleteStr> {
+        match self {
+            Operand::Register => recognize!(input, parse_register),
+            Operand::SingleRegister => recognize!(input, parse_single_register),
+            Operand::DoubleRegister => recognize!(input, parse_double_register),
+            Operand::Immediate(..) | Operand::Target(..) | Operand::Address(..) => {
+                recognize!(input, parse_expression)
+            }
+            Operand::Shift => recognize!(input, parse_shift),
+            Operand::ShiftAmount(..) => preceded!(input, pair!(tag_no_case!("LSL"), space), recognize!(parse_expression)),
+            Operand::String => recognize!(input, parse_string),
+            Operand::Symbol => parse_symbol(input),
+            Operand::Comma => tag!(input, ","),
+            Operand::OpenBracket => tag!(input, "["),
+            Operand::CloseBracket => tag!(input, "]"),
+        }
+    }
+}
+
+/// The operands an instruction or directive must have, followed by the ones
+/// it may have
+fn expected_operands(mnemonic: &str) -> Option<(&'static [Operand], &'static [Operand])> {
+    match mnemonic {
+        "ADD" | "SUB" | "AND" | "ORR" | "EOR" => Some((
+            &[Operand::Register, Operand::Comma, Operand::Register, Operand::Comma, Operand::Register],
+            &[Operand::Comma, Operand::ShiftAmount(Immediate6::MIN, Immediate6::MAX)]
+        )),
+        "ADDS" | "SUBS" | "ANDS" | "MUL" | "SMULH" | "UMULH" | "SDIV" | "UDIV" => Some((
+            &[Operand::Register, Operand::Comma, Operand::Register, Operand::Comma, Operand::Register],
+            &[]
+        )),
+        "ADDI" | "SUBI" | "ADDIS" | "SUBIS" | "ANDI" | "ORRI" | "EORI" | "ANDIS" => Some((
+            &[
+                Operand::Register, Operand::Comma, Operand::Register, Operand::Comma,
+                Operand::Immediate(Immediate12::MIN, Immediate12::MAX)
+            ],
+            &[]
+        )),
+        "LSR" | "LSL" => Some((
+            &[
+                Operand::Register, Operand::Comma, Operand::Register, Operand::Comma,
+                Operand::Immediate(Immediate6::MIN, Immediate6::MAX)
+            ],
+            &[]
+        )),
+        "STUR" | "LDUR" | "STURB" | "LDURB" | "STURW" | "LDURSW" | "STURH" | "LDURH" => Some((
+            &[
+                Operand::Register, Operand::Comma, Operand::OpenBracket, Operand::Register, Operand::Comma,
+                Operand::Address(Immediate9::MIN, Immediate9::MAX), Operand::CloseBracket
+            ],
+            &[]
+        )),
+        "LDXR" => Some((
+            &[
+                Operand::Register, Operand::Comma, Operand::OpenBracket, Operand::Register, Operand::Comma,
+                Operand::Immediate(0, 0), Operand::CloseBracket
+            ],
+            &[]
+        )),
+        "STXR" => Some((
+            &[
+                Operand::Register, Operand::Comma, Operand::Register, Operand::Comma, Operand::OpenBracket,
+                Operand::Register, Operand::Comma, Operand::Immediate(0, 0), Operand::CloseBracket
+            ],
+            &[]
+        )),
+        "FADDS" | "FSUBS" | "FMULS" | "FDIVS" => Some((
+            &[
+                Operand::SingleRegister, Operand::Comma, Operand::SingleRegister, Operand::Comma,
+                Operand::SingleRegister
+            ],
+            &[]
+        )),
+        "FADDD" | "FSUBD" | "FMULD" | "FDIVD" => Some((
+            &[
+                Operand::DoubleRegister, Operand::Comma, Operand::DoubleRegister, Operand::Comma,
+                Operand::DoubleRegister
+            ],
+            &[]
+        )),
+        "FCMPS" => Some((&[Operand::SingleRegister, Operand::Comma, Operand::SingleRegister], &[])),
+        "FCMPD" => Some((&[Operand::DoubleRegister, Operand::Comma, Operand::DoubleRegister], &[])),
+        "STURS" | "LDURS" => Some((
+            &[
+                Operand::SingleRegister, Operand::Comma, Operand::OpenBracket, Operand::Register, Operand::Comma,
+                Operand::Address(Immediate9::MIN, Immediate9::MAX), Operand::CloseBracket
+            ],
+            &[]
+        )),
+        "STURD" | "LDURD" => Some((
+            &[
+                Operand::DoubleRegister, Operand::Comma, Operand::OpenBracket, Operand::Register, Operand::Comma,
+                Operand::Address(Immediate9::MIN, Immediate9::MAX), Operand::CloseBracket
+            ],
+            &[]
+        )),
+        "MOVZ" | "MOVK" => Some((
+            &[Operand::Register, Operand::Comma, Operand::Address(Immediate16::MIN, Immediate16::MAX)],
+            &[Operand::Comma, Operand::Shift]
+        )),
+        "CBZ" | "CBNZ" => Some((
+            &[Operand::Register, Operand::Comma, Operand::Target(Immediate19::MIN, Immediate19::MAX)],
+            &[]
+        )),
+        "B" | "BL" => Some((&[Operand::Target(Immediate26::MIN, Immediate26::MAX)], &[])),
+        "BR" => Some((&[Operand::Register], &[])),
+        "MOV" | "CMP" | "NEG" => Some((&[Operand::Register, Operand::Comma, Operand::Register], &[])),
+        "CMPI" => Some((
+            &[Operand::Register, Operand::Comma, Operand::Immediate(Immediate12::MIN, Immediate12::MAX)],
+            &[]
+        )),
+        "NOP" => Some((&[], &[])),
+        "LDI" => Some((&[Operand::Register, Operand::Comma, Operand::Immediate(i64::MIN, i64::MAX)], &[])),
+        ".byte" => Some((&[Operand::Immediate(-128, 255)], &[Operand::Comma, Operand::Immediate(-128, 255)])),
+        ".word" => Some((
+            &[Operand::Immediate(-(1 << 31), (1 << 32) - 1)],
+            &[Operand::Comma, Operand::Immediate(-(1 << 31), (1 << 32) - 1)]
+        )),
+        ".dword" => Some((
+            &[Operand::Immediate(i64::MIN, i64::MAX)],
+            &[Operand::Comma, Operand::Immediate(i64::MIN, i64::MAX)]
+        )),
+        ".ascii" => Some((&[Operand::String], &[])),
+        ".space" => Some((&[Operand::Immediate(0, MAX_SPACE)], &[Operand::Comma, Operand::Immediate(-128, 255)])),
+        ".align" => Some((&[Operand::Immediate(0, MAX_ALIGN)], &[])),
+        ".text" | ".data" => Some((&[], &[])),
+        ".equ" | ".set" => Some((
+            &[Operand::Symbol, Operand::Comma, Operand::Immediate(i64::MIN, i64::MAX)],
+            &[]
+        )),
+        _ => None,
+    }
+}
+
+/// Whether the optional operands can be repeated, like the values of `.word`
+fn repeats_operands(mnemonic: &str) -> bool {
+    matches!(mnemonic, ".byte" | ".word" | ".dword")
+}
+
+/// The text of the operand at the start of `input`, for pointing at in an error
+fn next_operand(input: &str) -> &str {
+    match input.find(|c: char| c == ',' || c == '[' || c == ']' || c.is_whitespace()) {
+        Some(0) => &input[..input.chars().next().map_or(0, char::len_utf8)],
+        Some(end) => &input[..end],
+        None => input,
+    }
+}
+
+/// Find where the comment on a line starts, skipping over quoted text
+fn comment_start(line: &str) -> usize {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+
+    for (index, c) in line.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == ';' || c == '@' || line[index..].starts_with("//") => return index,
+            None => {}
+        }
+    }
+
+    line.len()
+}
+
+/// Split the label definitions off the start of a line, returning them and
+/// the rest of the line
+fn split_labels(line: &str) -> (Vec<&str>, CompleteStr<'_>) {
+    let mut labels = Vec::new();
+    let mut input = CompleteStr(line);
+    while let Ok((rest, label)) = parse_label_definition(input) {
+        labels.push(label.0);
+        input = rest;
+    }
+    (labels, input)
+}
+
+/// Work out why a line did not parse, returning the offending text and a message
+fn diagnose_line(line: &str) -> (&str, String) {
+    let (_, input) = split_labels(line);
+
+    // Anything else that looks like a label definition is a register name
+    if let Ok((_, name)) = ws!(input, terminated!(parse_label, tag!(":"))) {
+        return (name.0, format!("`{}` is a register, so it can not be used as a label", name.0));
+    }
+
+    // Leave off any comment
+    let statement = input.0[..comment_start(input.0)].trim();
+
+    let mnemonic = &statement[..statement.find(char::is_whitespace).unwrap_or(statement.len())];
+
+    // Instructions can be in any case, but directives must be lower case
+    let known = if mnemonic.starts_with('.') { mnemonic.to_owned() } else { mnemonic.to_ascii_uppercase() };
+
+    let (required, optional) = if known.starts_with("B.") {
+        let condition = &mnemonic[2..];
+
+        // A target run into the condition, like `B.NEloop`
+        if let Some((known, rest)) = condition.get(..2).map(|known| (known, &condition[2..])) {
+            if !rest.is_empty() && parse_condition(CompleteStr(known)).is_ok() {
+                return (rest, format!("expected space after `B.{}`", known));
+            }
+        }
+
+        if condition.len() != 2 || parse_condition(CompleteStr(condition)).is_err() {
+            return (condition, format!("unknown condition `{}`", condition));
+        }
+        (&[Operand::Target(Immediate19::MIN, Immediate19::MAX)][..], &[][..])
+    } else if let Some(operands) = expected_operands(&known) {
+        operands
+    } else if mnemonic.starts_with('.') {
+        return (mnemonic, format!("unknown directive `{}`", mnemonic));
+    } else {
+        return (mnemonic, format!("unknown mnemonic `{}`", mnemonic));
+    };
+
+    let mut input = CompleteStr(&statement[mnemonic.len()..]);
+    let mut previous = mnemonic;
+
+    let repeats = if repeats_operands(mnemonic) { usize::MAX } else { 1 };
+    let operands = required.iter().chain(optional.iter().cycle().take(optional.len().saturating_mul(repeats)));
+
+    for (index, &operand) in operands.enumerate() {
+        input = CompleteStr(input.0.trim_start());
+
+        // Stop at the end of the line if it is between groups of optional operands
+        if index >= required.len() && (index - required.len()) % optional.len() == 0 && input.0.is_empty() {
+            break;
+        }
+
+        match operand.parse(input) {
+            Ok((rest, parsed)) => {
+                if let (Some((min, max)), Ok((_, value))) = (operand.range(), parse_constant(parsed)) {
+                    if value < min || value > max {
+                        return (parsed.0, format!(
+                            "immediate `{}` is out of range for `{}`, expected {} to {}",
+                            parsed.0, mnemonic, min, max
+                        ));
+                    }
+                }
+
+                input = rest;
+                previous = parsed.0;
+            }
+            // At the end of the line, point at the last thing on it
+            Err(_) if input.0.is_empty() => return (
+                previous,
+                format!("expected {} after `{}`", operand.description(), previous)
+            ),
+            Err(_) => return (
+                next_operand(input.0),
+                format!("expected {} after `{}`", operand.description(), previous)
+            ),
+        }
+    }
+
+    let rest = input.0.trim();
+    if !rest.is_empty() {
+        return (rest, format!("unexpected `{}` after `{}`", rest, previous));
+    }
+
+    let operands = statement[mnemonic.len()..].trim();
+    (operands, format!("invalid operands for `{}`", mnemonic))
+}
+
+/// Build an error about `text`, which must be part of the line at `index`
+fn line_error(index: usize, line: &str, text: &str, message: String) -> Diagnostic {
+    let offset = text.as_ptr() as usize - line.as_ptr() as usize;
+    Diagnostic::error(index + 1, line[..offset].chars().count() + 1, text, message)
+}
+
+/// How deep constants can be defined in terms of other constants
+const MAX_DEPTH: usize = 64;
+
+/// The labels and constants defined in a program
+struct Symbols<'a> {
+    /// The section and address of each label
+    labels: HashMap<&'a str, (Section, i32)>,
+    constants: HashMap<&'a str, Expression<'a>>,
+}
+
+impl<'a> Symbols<'a> {
+    fn value(&self, name: &'a str, depth: usize) -> Result<i64, ExpressionError<'a>> {
+        if let Some(&(_, address)) = self.labels.get(name) {
+            Ok(i64::from(address))
+        } else if let Some(expression) = self.constants.get(name) {
+            if depth >= MAX_DEPTH {
+                return Err(ExpressionError::Recursive(name));
+            }
+            expression.evaluate(&|name| self.value(name, depth + 1))
+        } else {
+            Err(ExpressionError::Undefined(name))
+        }
+    }
+
+    /// The number of labels the expression adds, less the number it
+    /// subtracts, itself or through constants
+    ///
+    /// The labels in `end - start` cancel out, so it is 0 and the expression
+    /// is a size rather than an address. Any other operator on a label counts
+    /// as 1, leaving the label unpaired.
+    fn unpaired_labels(&self, expression: &Expression<'a>) -> i64 {
+        match *expression {
+            Expression::Number(_) => 0,
+            Expression::Symbol(name) => {
+                if self.labels.contains_key(name) {
+                    1
+                } else {
+                    self.constants.get(name).map_or(0, |constant| self.unpaired_labels(constant))
+                }
+            }
+            Expression::Unary(operator, ref operand) => match (operator, self.unpaired_labels(operand)) {
+                (_, 0) => 0,
+                (UnaryOperator::Negate, labels) => -labels,
+                (UnaryOperator::Not, _) => 1,
+            },
+            Expression::Binary(operator, ref left, ref right) => {
+                match (operator, self.unpaired_labels(left), self.unpaired_labels(right)) {
+                    (BinaryOperator::Add, left, right) => left + right,
+                    (BinaryOperator::Subtract, left, right) => left - right,
+                    (_, 0, 0) => 0,
+                    _ => 1,
+                }
+            }
+        }
+    }
+
+    /// Work out the value of an expression from the labels and constants
+    fn evaluate(&self, expression: &Expression<'a>) -> Result<i64, ExpressionError<'a>> {
+        expression.evaluate(&|name| self.value(name, 0))
+    }
+}
+
+impl<'a> Symbolic<'a> {
+    /// Fill in the immediate of the instruction at `address`, or explain why it
+    /// can not be
+    ///
+    /// Branches using labels go to the address the expression gives, while
+    /// numbers, constants and differences of labels are offsets. `MOVZ` and `MOVK` use the 16 bits
+    /// of an address their shift picks out.
+    fn resolve(&self, address: i32, symbols: &Symbols<'a>) -> Result<Instruction, String> {
+        let value = symbols.evaluate(&self.expression).map_err(|error| error.to_string())?;
+        let is_address = symbols.unpaired_labels(&self.expression) != 0;
+
+        // Errors about a label on its own name the label
+        let label = match self.expression {
+            Expression::Symbol(name) => symbols.labels.get(name).map(|&(section, _)| (name, section)),
+            _ => None,
+        };
+        let described = match label {
+            Some((name, _)) => format!("label `{}`", name),
+            None => format!("`{}`", self.text),
+        };
+
+        if self.instruction.is_branch() && is_address {
+            if let Some((name, Section::Data)) = label {
+                return Err(format!("label `{}` is in the `.data` section, so it can not be branched to", name));
+            }
+
+            let distance = value.checked_sub(i64::from(address))
+                .ok_or_else(|| format!("{} is too far away to branch to", described))?;
+            if distance % 4 != 0 {
+                return Err(format!("{} is not aligned to an instruction", described));
+            }
+
+            return self.instruction.with_immediate(distance / 4)
+                .ok_or_else(|| format!("{} is too far away to branch to", described));
+        }
+
+        let value = match self.instruction {
+            Instruction::MoveZero { shift, .. } | Instruction::MoveKeep { shift, .. } if is_address => {
+                (value >> (shift as u8 * 16)) & 0xFFFF
+            }
+            _ => value,
+        };
+
+        self.instruction.with_immediate(value).ok_or_else(|| {
+            let (min, max) = self.instruction.immediate_range().unwrap_or((0, 0));
+
+            match (label, &self.expression) {
+                (Some((name, _)), _) => format!(
+                    "label `{}` is at address {}, too far to use as an offset, load it with `MOVZ` instead",
+                    name, value
+                ),
+                (None, &Expression::Number(_)) => format!(
+                    "immediate `{}` is out of range for `{}`, expected {} to {}",
+                    self.text, self.mnemonic, min, max
+                ),
+                (None, _) => format!(
+                    "`{}` is {}, out of range for `{}`, expected {} to {}",
+                    self.text, value, self.mnemonic, min, max
+                ),
+            }
+        })
+    }
+}
+
+/// The `MOVZ` and `MOVK` instructions that load `value` into `destination`
+///
+/// Unless `every_part` is set, `MOVK` is left out for the 16 bit parts that
+/// are zero, so small values only need one instruction.
+fn load_immediate(destination: Register, value: i64, every_part: bool) -> Vec<Instruction> {
+    let part = |shift: u8| Immediate16::new((value as u64 >> (shift * 16)) as i64 & 0xFFFF).unwrap_or_default();
+
+    let mut instructions = vec![Instruction::MoveZero { immediate: part(0), shift: Shift16::Shift0, destination }];
+    for shift in 1..4 {
+        if every_part || part(shift).0 != 0 {
+            let shift16 = Shift16::from_u8(shift).unwrap_or(Shift16::Shift0);
+            instructions.push(Instruction::MoveKeep { immediate: part(shift), shift: shift16, destination });
+        }
+    }
+
+    instructions
+}
+
+/// Parse lines to instructions and data
+///
+/// Labels point at the byte address of the instruction or data after them,
+/// counting from the start of their section, and constants from `.equ` and
+/// `.set` can be used anywhere in the program. Branches to labels are
+/// resolved into offsets from the branch to the instruction after the label,
+/// other expressions using labels and constants into their values, and
+/// `.align` into the padding it needs. Lines that do not parse, use an
+/// undefined label or constant, branch to an unaligned or data label, define
+/// a label or constant a second time, have a value that does not fit, or have
+/// an instruction that is not on a word boundary or is in the data section
+/// become `AsmLine::Error` with a diagnostic explaining the problem. Lines
+/// that do not parse keep their labels in front of the error.
+///
+/// `LDI` becomes as few instructions as can load its value, or all four when
+/// the value uses a label after it, so the addresses of the labels after it
+/// do not depend on the value.
+pub fn parse_lines(lines: &str) -> Vec<AsmLine<'_>> {
+    let lines: Vec<&str> = lines.lines().collect();
+
+    let mut asm_lines: Vec<AsmLine> = lines.iter().enumerate().map(|(index, line)| {
+        if let Ok((_, asm_line)) = parse_line(CompleteStr(line)) {
+            asm_line
+        } else {
+            let (text, message) = diagnose_line(line);
+            let error = AsmLine::Error(line_error(index, line, text, message));
+
+            // Keep the labels, so branches to them are not reported as well
+            let (labels, _) = split_labels(line);
+            labels.into_iter().rev().fold(error, |line, label| AsmLine::Label(label, Box::new(line)))
+        }
+    }).collect();
+
+    let mut symbols = Symbols { labels: HashMap::new(), constants: HashMap::new() };
+    let mut errors: Vec<(usize, Diagnostic)> = Vec::new();
+
+    // The line each label and constant is defined on
+    let mut defined: HashMap<&str, usize> = HashMap::new();
+
+    // Find the constants first, so they can be used before they are defined
+    for (index, asm_line) in asm_lines.iter_mut().enumerate() {
+        if let AsmLine::Constant(name, ref value) = *asm_line.statement_mut() {
+            if let Some(&line) = defined.get(name) {
+                errors.push((index, line_error(
+                    index, lines[index], name,
+                    format!("constant `{}` is already defined on line {}", name, line + 1)
+                )));
+            } else {
+                defined.insert(name, index);
+                symbols.constants.insert(name, value.clone());
+            }
+        }
+    }
+
+    // Find the section and address of the instruction or data each label points to
+    let mut section = Section::Text;
+    let mut addresses = [0, 0];
+
+    for (index, asm_line) in asm_lines.iter_mut().enumerate() {
+        let address = &mut addresses[section as usize];
+
+        for label in asm_line.labels() {
+            if let Some(&line) = defined.get(label) {
+                errors.push((index, line_error(
+                    index, lines[index], label,
+                    format!("label `{}` is already defined on line {}", label, line + 1)
+                )));
+            } else {
+                defined.insert(label, index);
+                symbols.labels.insert(label, (section, *address));
+            }
+        }
+
+        let statement = asm_line.statement_mut();
+
+        if statement.is_instruction() {
+            if section == Section::Data {
+                let text = lines[index].trim();
+                errors.push((index, line_error(
+                    index, lines[index], text,
+                    "instructions can not go in the `.data` section".to_owned()
+                )));
+            } else if *address % 4 != 0 {
+                let text = lines[index].trim();
+                errors.push((index, line_error(
+                    index, lines[index], text,
+                    "instruction is not aligned to a word, add `.align 2` before it".to_owned()
+                )));
+            }
+        }
+
+        match *statement {
+            AsmLine::Instruction(_) | AsmLine::Symbolic(_) => *address += 4,
+            AsmLine::LoadImmediate(destination, ref value, text) => match symbols.evaluate(value) {
+                Ok(value) => {
+                    let instructions = load_immediate(destination, value, false);
+                    *address += 4 * instructions.len() as i32;
+                    *statement = AsmLine::Instructions(instructions);
+                }
+                // The value may use a label further on, so leave room for every part
+                Err(ExpressionError::Undefined(_)) => *address += 16,
+                Err(error) => {
+                    errors.push((index, line_error(index, lines[index], text, error.to_string())));
+                    *address += 16;
+                }
+            },
+            AsmLine::Align(alignment) => {
+                let size = 1 << alignment;
+                let padding = (size - *address % size) % size;
+                *statement = AsmLine::Data(vec![0; padding as usize]);
+                *address += padding;
+            }
+            AsmLine::Space(ref size, text, fill) => match symbols.evaluate(size) {
+                Ok(size) if (0..=MAX_SPACE).contains(&size) => {
+                    *statement = AsmLine::Data(vec![fill; size as usize]);
+                    *address += size as i32;
+                }
+                Ok(size) => errors.push((index, line_error(
+                    index, lines[index], text,
+                    format!("`{}` is {}, out of range for `.space`, expected 0 to {}", text, size, MAX_SPACE)
+                ))),
+                Err(error) => errors.push((index, line_error(index, lines[index], text, error.to_string()))),
+            },
+            AsmLine::Data(ref bytes) => *address += bytes.len() as i32,
+            AsmLine::Values(size, ref values) => *address += (size * values.len()) as i32,
+            AsmLine::Section(next) => section = next,
+            _ => {}
+        }
+    }
+
+    // Fill in the immediates and values that use labels and constants
+    let mut section = Section::Text;
+    let mut addresses = [0, 0];
+
+    for (index, asm_line) in asm_lines.iter_mut().enumerate() {
+        let address = &mut addresses[section as usize];
+        let statement = asm_line.statement_mut();
+
+        match *statement {
+            AsmLine::Instruction(_) => *address += 4,
+            AsmLine::Symbolic(ref symbolic) => {
+                match symbolic.resolve(*address, &symbols) {
+                    Ok(instruction) => *statement = AsmLine::Instruction(instruction),
+                    Err(message) => errors.push((index, line_error(index, lines[index], symbolic.text, message))),
+                }
+                *address += 4;
+            }
+            AsmLine::LoadImmediate(destination, ref value, text) => {
+                match symbols.evaluate(value) {
+                    Ok(value) => *statement = AsmLine::Instructions(load_immediate(destination, value, true)),
+                    Err(error) => errors.push((index, line_error(index, lines[index], text, error.to_string()))),
+                }
+                *address += 16;
+            }
+            AsmLine::Instructions(ref instructions) => *address += 4 * instructions.len() as i32,
+            AsmLine::Values(size, ref values) => {
+                let (min, max) = data_range(size);
+                let mut bytes = Vec::new();
+
+                for &(ref value, text) in values {
+                    match symbols.evaluate(value) {
+                        Ok(value) if (min..=max).contains(&value) => bytes.extend_from_slice(&value.to_le_bytes()[..size]),
+                        Ok(value) => errors.push((index, line_error(
+                            index, lines[index], text,
+                            format!("`{}` is {}, which does not fit in {} bytes", text, value, size)
+                        ))),
+                        Err(error) => errors.push((index, line_error(index, lines[index], text, error.to_string()))),
+                    }
+                }
+
+                *address += (size * values.len()) as i32;
+                *statement = AsmLine::Data(bytes);
+            }
+            AsmLine::Data(ref bytes) => *address += bytes.len() as i32,
+            AsmLine::Section(next) => section = next,
+            _ => {}
+        }
+    }
+
+    // Only report the first error on each line
+    for (index, diagnostic) in errors.into_iter().rev() {
+        asm_lines[index] = AsmLine::Error(diagnostic);
+    }
+
+    asm_lines
+}
+
+#[test]
+fn test_lines_parse() {
+    assert_eq!(
+        parse_lines("ADD X1, X2, X3\n\nSUB X4, X5, X6"),
+        vec![
+            AsmLine::Instruction(Instruction::Add { n: Register::X2, m: Register::X3, shift: Immediate6(0), destination: Register::X1 }),
+            AsmLine::Blank,
+            AsmLine::Instruction(Instruction::Subtract { n: Register::X5, m: Register::X6, shift: Immediate6(0), destination: Register::X4 })
+        ]
+    );
+}
+
+#[test]
+fn test_line_instruction_parse() {
+    assert_eq!(
+        parse_line(CompleteStr("ADD X1, X2, X3")),
+        Ok((
+            CompleteStr(""),
+            AsmLine::Instruction(Instruction::Add { n: Register::X2, m: Register::X3, shift: Immediate6(0), destination: Register::X1 }),
+        ))
+    );
+}
+
+#[test]
+fn test_line_empty_parse() {
+    assert_eq!(
+        parse_line(CompleteStr(" ")),
+        Ok((
+            CompleteStr(""),
+            AsmLine::Blank
+        ))
+    );
+}
+
+#[test]
+fn test_line_error_parse() {
+    assert!(
+        parse_line(CompleteStr("asdfasdf")).is_err()
+    );
+}
+
+#[test]
+fn test_line_label_parse() {
+    assert_eq!(
+        parse_line(CompleteStr("loop: ADD X1, X2, X3")),
+        Ok((
+            CompleteStr(""),
+            AsmLine::Label(
+                "loop",
+                Box::new(AsmLine::Instruction(Instruction::Add { n: Register::X2, m: Register::X3, shift: Immediate6(0), destination: Register::X1 }))
+            )
+        ))
+    );
+}
+
+#[test]
+fn test_line_label_only_parse() {
+    assert_eq!(
+        parse_line(CompleteStr("end:")),
+        Ok((
+            CompleteStr(""),
+            AsmLine::Label("end", Box::new(AsmLine::Blank))
+        ))
+    );
+}
+
+#[test]
+fn test_line_comment_parse() {
+    assert_eq!(
+        parse_line(CompleteStr("    // Count down to zero ")),
+        Ok((CompleteStr(""), AsmLine::Comment("Count down to zero")))
+    );
+}
+
+#[test]
+fn test_line_semicolon_comment_parse() {
+    assert_eq!(
+        parse_line(CompleteStr("; Count down to zero")),
+        Ok((CompleteStr(""), AsmLine::Comment("Count down to zero")))
+    );
+}
+
+#[test]
+fn test_line_at_comment_parse() {
+    assert_eq!(
+        parse_line(CompleteStr("@ Count down to zero")),
+        Ok((CompleteStr(""), AsmLine::Comment("Count down to zero")))
+    );
+}
+
+#[test]
+fn test_line_trailing_comment_parse() {
+    assert_eq!(
+        parse_line(CompleteStr("ADD X1, X2, X3 // X1 = X2 + X3")),
+        Ok((
+            CompleteStr(""),
+            AsmLine::Commented(
+                Box::new(AsmLine::Instruction(Instruction::Add { n: Register::X2, m: Register::X3, shift: Immediate6(0), destination: Register::X1 })),
+                "X1 = X2 + X3"
+            )
+        ))
+    );
+}
+
+#[test]
+fn test_line_label_comment_parse() {
+    assert_eq!(
+        parse_line(CompleteStr("loop: ; top of the loop")),
+        Ok((
+            CompleteStr(""),
+            AsmLine::Label("loop", Box::new(AsmLine::Comment("top of the loop")))
+        ))
+    );
+}
+
+#[test]
+fn test_line_trailing_junk_parse() {
+    assert!(
+        parse_line(CompleteStr("ADD X1, X2, X3 X4")).is_err()
+    );
+}
+
+#[test]
+fn test_lines_comment_parse() {
+    let lines = parse_lines("// Loop forever\nloop: B loop @ spin");
+    assert_eq!(lines[0].comment(), Some("Loop forever"));
+    assert_eq!(lines[1].comment(), Some("spin"));
+    assert_eq!(
+        lines,
+        vec![
+            AsmLine::Comment("Loop forever"),
+            AsmLine::Label(
+                "loop",
+                Box::new(AsmLine::Commented(
+                    Box::new(AsmLine::Instruction(Instruction::Branch { address: Immediate26(0_i32) })),
+                    "spin"
+                ))
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_label_parse() {
+    assert_eq!(
+        parse_lines("start:\n    CBZ X1, end\n    SUBI X1, X1, 1\n    B start\nend: BR X30"),
+        vec![
+            AsmLine::Label("start", Box::new(AsmLine::Blank)),
+            AsmLine::Instruction(Instruction::CompareBranchZero { address: Immediate19(3_i32), r: Register::X1 }),
+            AsmLine::Instruction(Instruction::SubtractImmediate { n: Register::X1, m: Immediate12(1_i16), destination: Register::X1 }),
+            AsmLine::Instruction(Instruction::Branch { address: Immediate26(-2_i32) }),
+            AsmLine::Label("end", Box::new(AsmLine::Instruction(Instruction::BranchRegister { r: Register::X30 }))),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_undefined_label_parse() {
+    assert_eq!(
+        parse_lines("B.EQ nowhere\n  BL nowhere"),
+        vec![
+            AsmLine::Error(Diagnostic::error(1, 6, "nowhere", "undefined symbol `nowhere`".to_owned())),
+            AsmLine::Error(Diagnostic::error(2, 6, "nowhere", "undefined symbol `nowhere`".to_owned())),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_duplicate_label_parse() {
+    assert_eq!(
+        parse_lines("here: B here\nhere: B here"),
+        vec![
+            AsmLine::Label("here", Box::new(AsmLine::Instruction(Instruction::Branch { address: Immediate26(0_i32) }))),
+            AsmLine::Error(Diagnostic::error(2, 1, "here", "label `here` is already defined on line 1".to_owned())),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_register_label_parse() {
+    assert_eq!(
+        parse_lines("x1: NOP\nstart: lr:\nD3:"),
+        vec![
+            AsmLine::Error(Diagnostic::error(1, 1, "x1", "`x1` is a register, so it can not be used as a label".to_owned())),
+            AsmLine::Label("start", Box::new(
+                AsmLine::Error(Diagnostic::error(2, 8, "lr", "`lr` is a register, so it can not be used as a label".to_owned()))
+            )),
+            AsmLine::Error(Diagnostic::error(3, 1, "D3", "`D3` is a register, so it can not be used as a label".to_owned())),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_unknown_mnemonic_parse() {
+    assert_eq!(
+        parse_lines("ADD X1, X2, X3\nloop: ADDX X1, X2, X3"),
+        vec![
+            AsmLine::Instruction(Instruction::Add { n: Register::X2, m: Register::X3, shift: Immediate6(0), destination: Register::X1 }),
+            AsmLine::Label("loop", Box::new(
+                AsmLine::Error(Diagnostic::error(2, 7, "ADDX", "unknown mnemonic `ADDX`".to_owned()))
+            )),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_error_keeps_label_parse() {
+    let diagnostics: Vec<Diagnostic> = parse_lines("foo: ADDX X1
+B foo
+CBZ X1, foo")
+        .into_iter()
+        .filter_map(|line| match line.into_statement() {
+            AsmLine::Error(diagnostic) => Some(diagnostic),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        diagnostics,
+        vec![Diagnostic::error(1, 6, "ADDX", "unknown mnemonic `ADDX`".to_owned())]
+    );
+}
+
+#[test]
+fn test_lines_unknown_condition_parse() {
+    assert_eq!(
+        parse_lines("B.XY 4"),
+        vec![AsmLine::Error(Diagnostic::error(1, 3, "XY", "unknown condition `XY`".to_owned()))]
+    );
+}
+
+#[test]
+fn test_lines_condition_without_space_parse() {
+    assert_eq!(
+        parse_lines("loop: B.NEloop"),
+        vec![AsmLine::Label("loop", Box::new(
+            AsmLine::Error(Diagnostic::error(1, 11, "loop", "expected space after `B.NE`".to_owned()))
+        ))]
+    );
+    assert_eq!(
+        parse_lines("B.EQ\t4"),
+        vec![AsmLine::Instruction(Instruction::ConditionalBranch { address: Immediate19(4), condition: Condition::Equal })]
+    );
+}
+
+#[test]
+fn test_lines_expected_register_parse() {
+    assert_eq!(
+        parse_lines("SUB X1, Y2, X3"),
+        vec![AsmLine::Error(Diagnostic::error(1, 9, "Y2", "expected register after `,`".to_owned()))]
+    );
+}
+
+#[test]
+fn test_lines_expected_bracket_parse() {
+    assert_eq!(
+        parse_lines("LDUR X1, [X2, 8 // load"),
+        vec![AsmLine::Error(Diagnostic::error(1, 15, "8", "expected `]` after `8`".to_owned()))]
+    );
+}
+
+#[test]
+fn test_lines_missing_operand_parse() {
+    assert_eq!(
+        parse_lines("ADD X1, X2\nBR"),
+        vec![
+            AsmLine::Error(Diagnostic::error(1, 9, "X2", "expected `,` after `X2`".to_owned())),
+            AsmLine::Error(Diagnostic::error(2, 1, "BR", "expected register after `BR`".to_owned())),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_expected_shift_parse() {
+    assert_eq!(
+        parse_lines("MOVZ X1, 5, LSL 8"),
+        vec![AsmLine::Error(Diagnostic::error(1, 13, "LSL", "expected `LSL 0`, `LSL 16`, `LSL 32` or `LSL 48` after `,`".to_owned()))]
+    );
+}
+
+#[test]
+fn test_lines_immediate_out_of_range_parse() {
+    assert_eq!(
+        parse_lines("ADDI X1, X1, 5000\nLSL X1, X1, 99\nSTUR X1, [X2, -300]"),
+        vec![
+            AsmLine::Error(Diagnostic::error(1, 14, "5000", "immediate `5000` is out of range for `ADDI`, expected 0 to 4095".to_owned())),
+            AsmLine::Error(Diagnostic::error(2, 13, "99", "immediate `99` is out of range for `LSL`, expected 0 to 63".to_owned())),
+            AsmLine::Error(Diagnostic::error(3, 15, "-300", "immediate `-300` is out of range for `STUR`, expected -256 to 255".to_owned())),
+        ]
+    );
+}
+
+#[test]
+fn test_label_branch_out_of_range() {
+    let symbols = Symbols {
+        labels: vec![("far", (Section::Text, 1 << 20))].into_iter().collect(),
+        constants: HashMap::new(),
+    };
+    let branch = |instruction, mnemonic| Symbolic { instruction, mnemonic, expression: Expression::Symbol("far"), text: "far" };
+
+    assert_eq!(
+        branch(Instruction::ConditionalBranch { address: Immediate19(0), condition: Condition::Equal }, "B.EQ")
+            .resolve(0, &symbols),
+        Err("label `far` is too far away to branch to".to_owned())
+    );
+    assert_eq!(
+        branch(Instruction::Branch { address: Immediate26(0) }, "B").resolve(0, &symbols),
+        Ok(Instruction::Branch { address: Immediate26(1 << 18) })
+    );
+}
+
+#[test]
+fn test_lines_unexpected_operand_parse() {
+    assert_eq!(
+        parse_lines("BR X30, X1"),
+        vec![AsmLine::Error(Diagnostic::error(1, 7, ", X1", "unexpected `, X1` after `X30`".to_owned()))]
+    );
+}
+
+#[test]
+fn test_byte_directive_parse() {
+    assert_eq!(
+        parse_line(CompleteStr(".byte 1, -1, 0xFF, 'A'")),
+        Ok((CompleteStr(""), AsmLine::Data(vec![1, 0xFF, 0xFF, 0x41])))
+    );
+}
+
+#[test]
+fn test_word_directive_parse() {
+    assert_eq!(
+        parse_line(CompleteStr(".word 0xDEADBEEF, -2")),
+        Ok((CompleteStr(""), AsmLine::Data(vec![0xEF, 0xBE, 0xAD, 0xDE, 0xFE, 0xFF, 0xFF, 0xFF])))
+    );
+}
+
+#[test]
+fn test_dword_directive_parse() {
+    assert_eq!(
+        parse_line(CompleteStr(".dword 0x0123456789ABCDEF")),
+        Ok((CompleteStr(""), AsmLine::Data(vec![0xEF, 0xCD, 0xAB, 0x89, 0x67, 0x45, 0x23, 0x01])))
+    );
+}
+
+#[test]
+fn test_ascii_directive_parse() {
+    assert_eq!(
+        parse_line(CompleteStr("msg: .ascii \"a;\\\"b\\n\" // greeting")),
+        Ok((
+            CompleteStr(""),
+            AsmLine::Label("msg", Box::new(AsmLine::Commented(
+                Box::new(AsmLine::Data(b"a;\"b\n".to_vec())),
+                "greeting"
+            )))
+        ))
+    );
+}
+
+#[test]
+fn test_space_directive_parse() {
+    assert_eq!(
+        parse_line(CompleteStr(".space 3, 0xAA")),
+        Ok((CompleteStr(""), AsmLine::Data(vec![0xAA, 0xAA, 0xAA])))
+    );
+    assert_eq!(
+        parse_line(CompleteStr(".space 2")),
+        Ok((CompleteStr(""), AsmLine::Data(vec![0, 0])))
+    );
+}
+
+#[test]
+fn test_lines_align_parse() {
+    assert_eq!(
+        parse_lines(".byte 1\n.align 3\n.align 3"),
+        vec![
+            AsmLine::Data(vec![1]),
+            AsmLine::Data(vec![0; 7]),
+            AsmLine::Data(vec![]),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_data_label_parse() {
+    assert_eq!(
+        parse_lines("B code\ndata: .word 7\ncode: CBZ X1, data"),
+        vec![
+            AsmLine::Instruction(Instruction::Branch { address: Immediate26(2_i32) }),
+            AsmLine::Label("data", Box::new(AsmLine::Data(vec![7, 0, 0, 0]))),
+            AsmLine::Label("code", Box::new(AsmLine::Instruction(
+                Instruction::CompareBranchZero { address: Immediate19(-1_i32), r: Register::X1 }
+            ))),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_unaligned_instruction_parse() {
+    assert_eq!(
+        parse_lines(".byte 1\n  ADD X1, X2, X3"),
+        vec![
+            AsmLine::Data(vec![1]),
+            AsmLine::Error(Diagnostic::error(
+                2, 3, "ADD X1, X2, X3",
+                "instruction is not aligned to a word, add `.align 2` before it".to_owned()
+            )),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_unaligned_label_parse() {
+    assert_eq!(
+        parse_lines(".byte 1\nodd: .byte 2\n.align 2\nB odd")[3],
+        AsmLine::Error(Diagnostic::error(4, 3, "odd", "label `odd` is not aligned to an instruction".to_owned()))
+    );
+}
+
+#[test]
+fn test_lines_unknown_directive_parse() {
+    assert_eq!(
+        parse_lines(".half 1"),
+        vec![AsmLine::Error(Diagnostic::error(1, 1, ".half", "unknown directive `.half`".to_owned()))]
+    );
+}
+
+#[test]
+fn test_lines_data_out_of_range_parse() {
+    assert_eq!(
+        parse_lines(".byte 1, 2, 256"),
+        vec![AsmLine::Error(Diagnostic::error(
+            1, 13, "256", "immediate `256` is out of range for `.byte`, expected -128 to 255".to_owned()
+        ))]
+    );
+}
+
+#[test]
+fn test_lines_expected_string_parse() {
+    assert_eq!(
+        parse_lines(".ascii hello"),
+        vec![AsmLine::Error(Diagnostic::error(1, 8, "hello", "expected string after `.ascii`".to_owned()))]
+    );
+}
+
+#[test]
+fn test_label_address_parse() {
+    assert_eq!(
+        parse_line(CompleteStr("MOVK X1, table, LSL 16")),
+        Ok((CompleteStr(""), AsmLine::Symbolic(Symbolic {
+            instruction: Instruction::MoveKeep { immediate: Immediate16(0), shift: Shift16::Shift16, destination: Register::X1 },
+            mnemonic: "MOVK",
+            expression: Expression::Symbol("table"),
+            text: "table",
+        })))
+    );
+    assert_eq!(
+        parse_line(CompleteStr("STURB X2, [X3, table]")),
+        Ok((CompleteStr(""), AsmLine::Symbolic(Symbolic {
+            instruction: Instruction::StoreByte { address: Register::X3, offset: Immediate9(0), data: Register::X2 },
+            mnemonic: "STURB",
+            expression: Expression::Symbol("table"),
+            text: "table",
+        })))
+    );
+}
+
+#[test]
+fn test_lines_sections_parse() {
+    assert_eq!(
+        parse_lines(".data\nfirst: .word 1\nsecond: .word 2\n.text\nMOVZ X1, second\nMOVK X1, second, LSL 16\nLDUR X2, [X1, second]"),
+        vec![
+            AsmLine::Section(Section::Data),
+            AsmLine::Label("first", Box::new(AsmLine::Data(vec![1, 0, 0, 0]))),
+            AsmLine::Label("second", Box::new(AsmLine::Data(vec![2, 0, 0, 0]))),
+            AsmLine::Section(Section::Text),
+            AsmLine::Instruction(Instruction::MoveZero { immediate: Immediate16(4), shift: Shift16::Shift0, destination: Register::X1 }),
+            AsmLine::Instruction(Instruction::MoveKeep { immediate: Immediate16(0), shift: Shift16::Shift16, destination: Register::X1 }),
+            AsmLine::Instruction(Instruction::Load { address: Register::X1, offset: Immediate9(4), data: Register::X2 }),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_instruction_in_data_parse() {
+    assert_eq!(
+        parse_lines(".data\nADD X1, X2, X3")[1],
+        AsmLine::Error(Diagnostic::error(
+            2, 1, "ADD X1, X2, X3", "instructions can not go in the `.data` section".to_owned()
+        ))
+    );
+}
+
+#[test]
+fn test_lines_branch_to_data_parse() {
+    assert_eq!(
+        parse_lines(".data\nvalue: .dword 1\n.text\nB value")[3],
+        AsmLine::Error(Diagnostic::error(
+            4, 3, "value", "label `value` is in the `.data` section, so it can not be branched to".to_owned()
+        ))
+    );
+}
+
+#[test]
+fn test_lines_label_address_out_of_range_parse() {
+    assert_eq!(
+        parse_lines(".data\n.space 256\nfar: .dword 1\n.text\nLDUR X1, [XZR, far]")[4],
+        AsmLine::Error(Diagnostic::error(
+            5, 16, "far",
+            "label `far` is at address 256, too far to use as an offset, load it with `MOVZ` instead".to_owned()
+        ))
+    );
+}
+
+#[test]
+fn test_lines_undefined_label_address_parse() {
+    assert_eq!(
+        parse_lines("MOVZ X1, nowhere"),
+        vec![AsmLine::Error(Diagnostic::error(1, 10, "nowhere", "undefined symbol `nowhere`".to_owned()))]
+    );
+}
+
+#[test]
+fn test_expression_parse() {
+    assert_eq!(parse_constant(CompleteStr("1 + 2 * 3")), Ok((CompleteStr(""), 7)));
+    assert_eq!(parse_constant(CompleteStr("(1 + 2) * 3")), Ok((CompleteStr(""), 9)));
+    assert_eq!(parse_constant(CompleteStr("1 << 4 | 0x3 & ~1")), Ok((CompleteStr(""), 18)));
+    assert_eq!(parse_constant(CompleteStr("-(8 - 10) / 2")), Ok((CompleteStr(""), 1)));
+    assert_eq!(parse_constant(CompleteStr("#16 >> 2")), Ok((CompleteStr(""), 4)));
+}
+
+#[test]
+fn test_expression_comment_parse() {
+    assert_eq!(parse_constant(CompleteStr("8 // half")), Ok((CompleteStr(" // half"), 8)));
+}
+
+#[test]
+fn test_expression_symbol_parse() {
+    assert_eq!(
+        parse_expression(CompleteStr("end - start")),
+        Ok((CompleteStr(""), Expression::Binary(
+            BinaryOperator::Subtract,
+            Box::new(Expression::Symbol("end")),
+            Box::new(Expression::Symbol("start"))
+        )))
+    );
+    assert!(parse_symbol(CompleteStr("X1")).is_err());
+    assert!(parse_symbol(CompleteStr("X1_loop")).is_ok());
+}
+
+#[test]
+fn test_constant_expression_instruction_parse() {
+    assert_eq!(
+        parse_instruction(CompleteStr("ADDI X1, X2, 4 * 8 + 1")),
+        Ok((CompleteStr(""), Instruction::AddImmediate { n: Register::X2, m: Immediate12(33), destination: Register::X1 }))
+    );
+}
+
+#[test]
+fn test_lines_branch_to_constant_parse() {
+    assert_eq!(
+        parse_lines(".equ OFF, 2\n.equ TARGET, here\nB OFF\nCBZ X1, OFF - 3\nhere: B TARGET"),
+        vec![
+            AsmLine::Constant("OFF", Expression::Number(2)),
+            AsmLine::Constant("TARGET", Expression::Symbol("here")),
+            AsmLine::Instruction(Instruction::Branch { address: Immediate26(2) }),
+            AsmLine::Instruction(Instruction::CompareBranchZero { address: Immediate19(-1), r: Register::X1 }),
+            AsmLine::Label("here", Box::new(AsmLine::Instruction(Instruction::Branch { address: Immediate26(0) }))),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_branch_label_difference_parse() {
+    assert_eq!(
+        parse_lines("start: B end - start\nCBZ X1, start - end\nend: MOVZ X1, (end - start) * 2")[..],
+        [
+            AsmLine::Label("start", Box::new(AsmLine::Instruction(Instruction::Branch { address: Immediate26(8) }))),
+            AsmLine::Instruction(Instruction::CompareBranchZero { address: Immediate19(-8), r: Register::X1 }),
+            AsmLine::Label("end", Box::new(AsmLine::Instruction(Instruction::MoveZero {
+                immediate: Immediate16(16),
+                shift: Shift16::Shift0,
+                destination: Register::X1,
+            }))),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_negative_label_difference_parse() {
+    assert_eq!(
+        parse_lines("start: ADD X1, X2, X3\nend: MOVZ X1, start - end")[1],
+        AsmLine::Error(Diagnostic::error(
+            2, 15, "start - end", "`start - end` is -4, out of range for `MOVZ`, expected 0 to 65535".to_owned()
+        ))
+    );
+}
+
+#[test]
+fn test_lines_equ_parse() {
+    assert_eq!(
+        parse_lines("ADDI X1, X1, SIZE * 2\n.equ SIZE, COUNT * 8\n.set COUNT, 4\nLDUR X2, [X1, -SIZE]"),
+        vec![
+            AsmLine::Instruction(Instruction::AddImmediate { n: Register::X1, m: Immediate12(64), destination: Register::X1 }),
+            AsmLine::Constant("SIZE", Expression::Binary(
+                BinaryOperator::Multiply,
+                Box::new(Expression::Symbol("COUNT")),
+                Box::new(Expression::Number(8))
+            )),
+            AsmLine::Constant("COUNT", Expression::Number(4)),
+            AsmLine::Instruction(Instruction::Load { address: Register::X1, offset: Immediate9(-32), data: Register::X2 }),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_label_difference_parse() {
+    assert_eq!(
+        parse_lines(".data\nstart: .word 1, 2, 3\nend:\n.text\nMOVZ X1, (end - start) / 4\nB done + 4\ndone: B done"),
+        vec![
+            AsmLine::Section(Section::Data),
+            AsmLine::Label("start", Box::new(AsmLine::Data(vec![1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0]))),
+            AsmLine::Label("end", Box::new(AsmLine::Blank)),
+            AsmLine::Section(Section::Text),
+            AsmLine::Instruction(Instruction::MoveZero { immediate: Immediate16(3), shift: Shift16::Shift0, destination: Register::X1 }),
+            AsmLine::Instruction(Instruction::Branch { address: Immediate26(2) }),
+            AsmLine::Label("done", Box::new(AsmLine::Instruction(Instruction::Branch { address: Immediate26(0) }))),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_symbolic_data_parse() {
+    assert_eq!(
+        parse_lines(".equ FILL, 0xAA\n.equ SIZE, 3\ntable: .byte SIZE, end - table\n.space SIZE, 1\nend:"),
+        vec![
+            AsmLine::Constant("FILL", Expression::Number(0xAA)),
+            AsmLine::Constant("SIZE", Expression::Number(3)),
+            AsmLine::Label("table", Box::new(AsmLine::Data(vec![3, 5]))),
+            AsmLine::Data(vec![1, 1, 1]),
+            AsmLine::Label("end", Box::new(AsmLine::Blank)),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_constant_out_of_range_parse() {
+    assert_eq!(
+        parse_lines(".equ BIG, 4096\nADDI X1, X1, BIG"),
+        vec![
+            AsmLine::Constant("BIG", Expression::Number(4096)),
+            AsmLine::Error(Diagnostic::error(
+                2, 14, "BIG", "`BIG` is 4096, out of range for `ADDI`, expected 0 to 4095".to_owned()
+            )),
+        ]
+    );
+    assert_eq!(
+        parse_lines(".equ BIG, 300\n.byte BIG")[1],
+        AsmLine::Error(Diagnostic::error(2, 7, "BIG", "`BIG` is 300, which does not fit in 1 bytes".to_owned()))
+    );
+}
+
+#[test]
+fn test_lines_recursive_constant_parse() {
+    assert_eq!(
+        parse_lines(".equ A, A + 1\nMOVZ X1, A")[1],
+        AsmLine::Error(Diagnostic::error(2, 10, "A", "constant `A` is defined in terms of itself".to_owned()))
+    );
+}
+
+#[test]
+fn test_lines_undefined_constant_parse() {
+    assert_eq!(
+        parse_lines(".equ A, B + 1\nMOVZ X1, A")[1],
+        AsmLine::Error(Diagnostic::error(2, 10, "A", "undefined symbol `B`".to_owned()))
+    );
+}
+
+#[test]
+fn test_lines_duplicate_constant_parse() {
+    assert_eq!(
+        parse_lines(".equ A, 1\n.set A, 2\nA: B A"),
+        vec![
+            AsmLine::Constant("A", Expression::Number(1)),
+            AsmLine::Error(Diagnostic::error(2, 6, "A", "constant `A` is already defined on line 1".to_owned())),
+            AsmLine::Error(Diagnostic::error(3, 1, "A", "label `A` is already defined on line 1".to_owned())),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_divide_by_zero_parse() {
+    assert_eq!(
+        parse_lines(".equ ZERO, 0\nADDI X1, X1, 8 / ZERO")[1],
+        AsmLine::Error(Diagnostic::error(2, 14, "8 / ZERO", "division by zero".to_owned()))
+    );
+}
+
+#[test]
+fn test_lines_expected_expression_parse() {
+    assert_eq!(
+        parse_lines("ADDI X1, X1, 4 +"),
+        vec![AsmLine::Error(Diagnostic::error(1, 14, "4", "expected immediate after `,`".to_owned()))]
+    );
+    assert_eq!(
+        parse_lines(".equ X1, 4"),
+        vec![AsmLine::Error(Diagnostic::error(1, 6, "X1", "expected name after `.equ`".to_owned()))]
+    );
+}
 
-/// Parse lines to instructions
-pub fn parse_lines(lines: &str) -> Vec<AsmLine> {
-    lines.lines().map(|line| {
-        if let Ok((_, asm_line)) = parse_line(CompleteStr(line)) {
-            asm_line
-        } else {
-            AsmLine::Error
-        }
-    }).collect()
+#[test]
+fn test_lower_case_instruction_parse() {
+    assert_eq!(
+        parse_instruction(CompleteStr("sturb x1, [sp, #8]")),
+        Ok((CompleteStr(""), Instruction::StoreByte { address: Register::X28, offset: Immediate9(8), data: Register::X1 }))
+    );
+    assert_eq!(
+        parse_instruction(CompleteStr("movk x2, 0x1234, lsl 16")),
+        Ok((CompleteStr(""), Instruction::MoveKeep { immediate: Immediate16(0x1234), shift: Shift16::Shift16, destination: Register::X2 }))
+    );
+    assert_eq!(
+        parse_instruction(CompleteStr("br lr")),
+        Ok((CompleteStr(""), Instruction::BranchRegister { r: Register::X30 }))
+    );
 }
 
 #[test]
-fn test_lines_parse() {
+fn test_lines_lower_case_parse() {
     assert_eq!(
-        parse_lines("ADD X1, X2, X3\n\nSUB X4, X5, X6"),
+        parse_lines("loop: subi x9, x9, 1\ncbnz x9, loop\nb.ne loop\nbl loop"),
         vec![
-            AsmLine::Instruction(Instruction::Add { n: Register::X2, m: Register::X3, destination: Register::X1 }),
-            AsmLine::Blank,
-            AsmLine::Instruction(Instruction::Subtract { n: Register::X5, m: Register::X6, destination: Register::X4 })
+            AsmLine::Label("loop", Box::new(AsmLine::Instruction(Instruction::SubtractImmediate {
+                n: Register::X9, m: Immediate12(1), destination: Register::X9
+            }))),
+            AsmLine::Instruction(Instruction::CompareBranchNotZero { address: Immediate19(-1), r: Register::X9 }),
+            AsmLine::Instruction(Instruction::ConditionalBranch { address: Immediate19(-2), condition: Condition::NotEqual }),
+            AsmLine::Instruction(Instruction::BranchLink { address: Immediate26(-3) }),
         ]
     );
 }
 
 #[test]
-fn test_line_instruction_parse() {
+fn test_lines_lower_case_errors_parse() {
     assert_eq!(
-        parse_line(CompleteStr("ADD X1, X2, X3")),
-        Ok((
-            CompleteStr(""),
-            AsmLine::Instruction(Instruction::Add { n: Register::X2, m: Register::X3, destination: Register::X1 }),
-        ))
+        parse_lines("addi x1, x2, 5000\nb.xx 4\naddx x1, x2, x3"),
+        vec![
+            AsmLine::Error(Diagnostic::error(
+                1, 14, "5000", "immediate `5000` is out of range for `addi`, expected 0 to 4095".to_owned()
+            )),
+            AsmLine::Error(Diagnostic::error(2, 3, "xx", "unknown condition `xx`".to_owned())),
+            AsmLine::Error(Diagnostic::error(3, 1, "addx", "unknown mnemonic `addx`".to_owned())),
+        ]
     );
 }
 
 #[test]
-fn test_line_empty_parse() {
+fn test_word_halfword_parse() {
     assert_eq!(
-        parse_line(CompleteStr(" ")),
-        Ok((
-            CompleteStr(""),
-            AsmLine::Blank
-        ))
+        parse_instruction(CompleteStr("LDURSW X1, [X2, #-4]")),
+        Ok((CompleteStr(""), Instruction::LoadSignedWord { address: Register::X2, offset: Immediate9(-4), data: Register::X1 }))
+    );
+    assert_eq!(
+        parse_instruction(CompleteStr("sturh x3, [sp, 6]")),
+        Ok((CompleteStr(""), Instruction::StoreHalfword { address: Register::X28, offset: Immediate9(6), data: Register::X3 }))
     );
 }
 
 #[test]
-fn test_line_error_parse() {
-    assert!(
-        parse_line(CompleteStr("asdfasdf")).is_err()
+fn test_lines_word_halfword_label_parse() {
+    assert_eq!(
+        parse_lines(".data\n.space 4\nvalue: .word -1\n.text\nLDURSW X1, [XZR, value]\nSTURH X1, [XZR, value + 2]\nLDURH X2, [XZR, 300]"),
+        vec![
+            AsmLine::Section(Section::Data),
+            AsmLine::Data(vec![0; 4]),
+            AsmLine::Label("value", Box::new(AsmLine::Data(vec![0xFF; 4]))),
+            AsmLine::Section(Section::Text),
+            AsmLine::Instruction(Instruction::LoadSignedWord { address: Register::XZR, offset: Immediate9(4), data: Register::X1 }),
+            AsmLine::Instruction(Instruction::StoreHalfword { address: Register::XZR, offset: Immediate9(6), data: Register::X1 }),
+            AsmLine::Error(Diagnostic::error(
+                7, 17, "300", "immediate `300` is out of range for `LDURH`, expected -256 to 255".to_owned()
+            )),
+        ]
+    );
+}
+
+#[test]
+fn test_exclusive_parse() {
+    assert_eq!(
+        parse_instruction(CompleteStr("LDXR X10, [X20, 0]")),
+        Ok((CompleteStr(""), Instruction::LoadExclusive { address: Register::X20, data: Register::X10 }))
+    );
+    assert_eq!(
+        parse_instruction(CompleteStr("STXR X9, X10, [X11, #0]")),
+        Ok((CompleteStr(""), Instruction::StoreExclusive { address: Register::X11, data: Register::X9, status: Register::X10 }))
+    );
+    assert!(parse_instruction(CompleteStr("LDXR X10, [X20, 8]")).is_err());
+}
+
+#[test]
+fn test_lines_exclusive_errors_parse() {
+    assert_eq!(
+        parse_lines("STXR X9, X10, [X11, 8]\nLDXR X1, X2"),
+        vec![
+            AsmLine::Error(Diagnostic::error(
+                1, 21, "8", "immediate `8` is out of range for `STXR`, expected 0 to 0".to_owned()
+            )),
+            AsmLine::Error(Diagnostic::error(2, 10, "X2", "expected `[` after `,`".to_owned())),
+        ]
+    );
+}
+
+#[test]
+fn test_float_register_parse() {
+    assert_eq!(parse_single_register(CompleteStr("S0")), Ok((CompleteStr(""), FloatRegister::F0)));
+    assert_eq!(parse_single_register(CompleteStr("s31")), Ok((CompleteStr(""), FloatRegister::F31)));
+    assert_eq!(parse_double_register(CompleteStr("D17")), Ok((CompleteStr(""), FloatRegister::F17)));
+    assert!(parse_double_register(CompleteStr("D32")).is_err());
+    assert!(parse_double_register(CompleteStr("D01")).is_err());
+    assert!(parse_single_register(CompleteStr("D1")).is_err());
+}
+
+#[test]
+fn test_float_parse() {
+    assert_eq!(
+        parse_instruction(CompleteStr("FADDD D1, D2, D3")),
+        Ok((CompleteStr(""), Instruction::FloatAddDouble {
+            n: FloatRegister::F2,
+            m: FloatRegister::F3,
+            destination: FloatRegister::F1
+        }))
+    );
+    assert_eq!(
+        parse_instruction(CompleteStr("fcmps s4, s5")),
+        Ok((CompleteStr(""), Instruction::FloatCompareSingle { n: FloatRegister::F4, m: FloatRegister::F5 }))
+    );
+    assert_eq!(
+        parse_instruction(CompleteStr("LDURS S1, [SP, #-4]")),
+        Ok((CompleteStr(""), Instruction::LoadSingle {
+            address: Register::X28,
+            offset: Immediate9(-4),
+            data: FloatRegister::F1
+        }))
+    );
+    assert!(parse_instruction(CompleteStr("FADDS S1, S2, D3")).is_err());
+}
+
+#[test]
+fn test_lines_float_parse() {
+    assert_eq!(
+        parse_lines(".data\nvalue: .dword 0\n.text\nLDURD D1, [XZR, value]\nFMULS S1, S2, X3\nSTURS D1, [X1, 0]"),
+        vec![
+            AsmLine::Section(Section::Data),
+            AsmLine::Label("value", Box::new(AsmLine::Data(vec![0; 8]))),
+            AsmLine::Section(Section::Text),
+            AsmLine::Instruction(Instruction::LoadDouble {
+                address: Register::XZR,
+                offset: Immediate9(0),
+                data: FloatRegister::F1
+            }),
+            AsmLine::Error(Diagnostic::error(5, 15, "X3", "expected `S` register after `,`".to_owned())),
+            AsmLine::Error(Diagnostic::error(6, 7, "D1", "expected `S` register after `STURS`".to_owned())),
+        ]
+    );
+}
+
+#[test]
+fn test_shifted_register_parse() {
+    assert_eq!(
+        parse_instruction(CompleteStr("ADD X1, X2, X3, LSL 4")),
+        Ok((CompleteStr(""), Instruction::Add {
+            n: Register::X2,
+            m: Register::X3,
+            shift: Immediate6(4),
+            destination: Register::X1
+        }))
+    );
+    assert_eq!(
+        parse_instruction(CompleteStr("orr x1, x2, x3 , lsl #63")),
+        Ok((CompleteStr(""), Instruction::Or {
+            n: Register::X2,
+            m: Register::X3,
+            shift: Immediate6(63),
+            destination: Register::X1
+        }))
+    );
+}
+
+#[test]
+fn test_lines_shifted_register_parse() {
+    assert_eq!(
+        parse_lines(".equ SCALE, 3\nSUB X1, X2, X3, LSL SCALE\nADD X1, X2, X3, LSL 64\nEOR X1, X2, X3, LSR 4"),
+        vec![
+            AsmLine::Constant("SCALE", Expression::Number(3)),
+            AsmLine::Instruction(Instruction::Subtract {
+                n: Register::X2,
+                m: Register::X3,
+                shift: Immediate6(3),
+                destination: Register::X1
+            }),
+            AsmLine::Error(Diagnostic::error(
+                3, 21, "64", "immediate `64` is out of range for `ADD`, expected 0 to 63".to_owned()
+            )),
+            AsmLine::Error(Diagnostic::error(
+                4, 17, "LSR", "expected `LSL` and a shift amount after `,`".to_owned()
+            )),
+        ]
+    );
+}
+
+#[test]
+fn test_multiply_divide_parse() {
+    assert_eq!(
+        parse_instruction(CompleteStr("MUL X1, X2, X3")),
+        Ok((CompleteStr(""), Instruction::Multiply { n: Register::X2, m: Register::X3, destination: Register::X1 }))
+    );
+    assert_eq!(
+        parse_instruction(CompleteStr("udiv x4, x5, x6")),
+        Ok((CompleteStr(""), Instruction::UnsignedDivide { n: Register::X5, m: Register::X6, destination: Register::X4 }))
+    );
+}
+
+#[test]
+fn test_pseudo_instruction_parse() {
+    assert_eq!(
+        parse_instruction(CompleteStr("MOV X1, X2")),
+        Ok((CompleteStr(""), Instruction::Or { n: Register::XZR, m: Register::X2, shift: Immediate6(0), destination: Register::X1 }))
+    );
+    assert_eq!(
+        parse_instruction(CompleteStr("CMP X1, X2")),
+        Ok((CompleteStr(""), Instruction::SubtractSetFlags { n: Register::X1, m: Register::X2, destination: Register::XZR }))
+    );
+    assert_eq!(
+        parse_instruction(CompleteStr("CMPI X1, 5")),
+        Ok((CompleteStr(""), Instruction::SubtractImmediateSetFlags {
+            n: Register::X1, m: Immediate12(5), destination: Register::XZR
+        }))
+    );
+    assert_eq!(
+        parse_instruction(CompleteStr("NOP")),
+        Ok((CompleteStr(""), Instruction::Add { n: Register::XZR, m: Register::XZR, shift: Immediate6(0), destination: Register::XZR }))
+    );
+    assert_eq!(
+        parse_instruction(CompleteStr("neg x3, x4")),
+        Ok((CompleteStr(""), Instruction::Subtract { n: Register::XZR, m: Register::X4, shift: Immediate6(0), destination: Register::X3 }))
+    );
+}
+
+#[test]
+fn test_load_immediate() {
+    assert_eq!(
+        load_immediate(Register::X0, 0x1234_0000_5678, false),
+        vec![
+            Instruction::MoveZero { immediate: Immediate16(0x5678), shift: Shift16::Shift0, destination: Register::X0 },
+            Instruction::MoveKeep { immediate: Immediate16(0x1234), shift: Shift16::Shift32, destination: Register::X0 },
+        ]
+    );
+    assert_eq!(
+        load_immediate(Register::X0, 0, false),
+        vec![Instruction::MoveZero { immediate: Immediate16(0), shift: Shift16::Shift0, destination: Register::X0 }]
+    );
+    assert_eq!(load_immediate(Register::X0, -1, false).len(), 4);
+    assert_eq!(load_immediate(Register::X0, 7, true).len(), 4);
+}
+
+#[test]
+fn test_lines_load_immediate_parse() {
+    assert_eq!(
+        parse_lines("LDI X0, 0x123456789ABC\nB end\nLDI X1, end\nend: CMPI X0, 1"),
+        vec![
+            AsmLine::Instructions(vec![
+                Instruction::MoveZero { immediate: Immediate16(0x9ABCu16 as i16), shift: Shift16::Shift0, destination: Register::X0 },
+                Instruction::MoveKeep { immediate: Immediate16(0x5678), shift: Shift16::Shift16, destination: Register::X0 },
+                Instruction::MoveKeep { immediate: Immediate16(0x1234), shift: Shift16::Shift32, destination: Register::X0 },
+            ]),
+            AsmLine::Instruction(Instruction::Branch { address: Immediate26(5) }),
+            AsmLine::Instructions(vec![
+                Instruction::MoveZero { immediate: Immediate16(32), shift: Shift16::Shift0, destination: Register::X1 },
+                Instruction::MoveKeep { immediate: Immediate16(0), shift: Shift16::Shift16, destination: Register::X1 },
+                Instruction::MoveKeep { immediate: Immediate16(0), shift: Shift16::Shift32, destination: Register::X1 },
+                Instruction::MoveKeep { immediate: Immediate16(0), shift: Shift16::Shift48, destination: Register::X1 },
+            ]),
+            AsmLine::Label("end", Box::new(AsmLine::Instruction(Instruction::SubtractImmediateSetFlags {
+                n: Register::X0, m: Immediate12(1), destination: Register::XZR
+            }))),
+        ]
+    );
+}
+
+#[test]
+fn test_lines_pseudo_instruction_errors_parse() {
+    assert_eq!(
+        parse_lines("CMPI X1, 5000\nLDI X2, nowhere\nNOP X1"),
+        vec![
+            AsmLine::Error(Diagnostic::error(
+                1, 10, "5000", "immediate `5000` is out of range for `CMPI`, expected 0 to 4095".to_owned()
+            )),
+            AsmLine::Error(Diagnostic::error(2, 9, "nowhere", "undefined symbol `nowhere`".to_owned())),
+            AsmLine::Error(Diagnostic::error(3, 5, "X1", "unexpected `X1` after `NOP`".to_owned())),
+        ]
+    );
+}
+
+#[test]
+fn test_conditional_label_branch_parse() {
+    assert_eq!(
+        parse_line(CompleteStr("B.NE loop")),
+        Ok((CompleteStr(""), AsmLine::Symbolic(Symbolic {
+            instruction: Instruction::ConditionalBranch { address: Immediate19(0), condition: Condition::NotEqual },
+            mnemonic: "B.NE",
+            expression: Expression::Symbol("loop"),
+            text: "loop",
+        })))
+    );
+}
+
+#[test]
+fn test_label_branch_parse() {
+    assert_eq!(
+        parse_line(CompleteStr("B LOOP")),
+        Ok((CompleteStr(""), AsmLine::Symbolic(Symbolic {
+            instruction: Instruction::Branch { address: Immediate26(0) },
+            mnemonic: "B",
+            expression: Expression::Symbol("LOOP"),
+            text: "LOOP",
+        })))
+    );
+}
+
+#[test]
+fn test_compare_branch_zero_label_parse() {
+    assert_eq!(
+        parse_line(CompleteStr("CBZ X3, done")),
+        Ok((CompleteStr(""), AsmLine::Symbolic(Symbolic {
+            instruction: Instruction::CompareBranchZero { address: Immediate19(0), r: Register::X3 },
+            mnemonic: "CBZ",
+            expression: Expression::Symbol("done"),
+            text: "done",
+        })))
     );
 }
 
@@ -425,6 +2795,14 @@ fn test_branch_link_parse() {
     );
 }
 
+#[test]
+fn test_branch_link_spaces_parse() {
+    assert_eq!(
+        parse_instruction(CompleteStr("BL  4")),
+        Ok((CompleteStr(""), Instruction::BranchLink { address: Immediate26(4) }))
+    );
+}
+
 #[test]
 fn test_branch_register_parse() {
     assert_eq!(
@@ -602,6 +2980,7 @@ fn test_and_parse() {
             Instruction::And {
                 n: Register::X2,
                 m: Register::X3,
+                shift: Immediate6(0),
                 destination: Register::X1,
             }
         ))
@@ -617,6 +2996,7 @@ fn test_or_parse() {
             Instruction::Or {
                 n: Register::X2,
                 m: Register::X3,
+                shift: Immediate6(0),
                 destination: Register::X1,
             }
         ))
@@ -632,6 +3012,7 @@ fn test_xor_parse() {
             Instruction::Xor {
                 n: Register::X2,
                 m: Register::X3,
+                shift: Immediate6(0),
                 destination: Register::X1,
             }
         ))
@@ -857,12 +3238,37 @@ fn test_add_parse() {
             Instruction::Add {
                 n: Register::X2,
                 m: Register::X3,
+                shift: Immediate6(0),
+                destination: Register::X1,
+            }
+        ))
+    );
+}
+
+#[test]
+fn test_add_spaces_parse() {
+    assert_eq!(
+        parse_instruction(CompleteStr("ADD  \t X1, X2, X3")),
+        Ok((
+            CompleteStr(""),
+            Instruction::Add {
+                n: Register::X2,
+                m: Register::X3,
+                shift: Immediate6(0),
                 destination: Register::X1,
             }
         ))
     );
 }
 
+#[test]
+fn test_branch_register_tabs_parse() {
+    assert_eq!(
+        parse_instruction(CompleteStr("BR\t\tX30")),
+        Ok((CompleteStr(""), Instruction::BranchRegister { r: Register::X30 }))
+    );
+}
+
 #[test]
 fn test_subract_parse() {
     assert_eq!(
@@ -872,6 +3278,7 @@ fn test_subract_parse() {
             Instruction::Subtract {
                 n: Register::X2,
                 m: Register::X3,
+                shift: Immediate6(0),
                 destination: Register::X1,
             }
         ))
@@ -898,6 +3305,11 @@ fn test_shift48_parse() {
     assert_eq!(parse_shift(CompleteStr("LSL 48")), Ok((CompleteStr(""), Shift16::Shift48,)));
 }
 
+#[test]
+fn test_condition_lower_case_parse() {
+    assert_eq!(parse_condition(CompleteStr("ne")), Ok((CompleteStr(""), Condition::NotEqual)));
+}
+
 #[test]
 fn test_condition_equal_parse() {
     assert_eq!(parse_condition(CompleteStr("EQ")), Ok((CompleteStr(""), Condition::Equal)));
@@ -1061,9 +3473,8 @@ fn test_immediate26_parse() {
 
 #[test]
 fn test_negitive_immediate6_parse() {
-    assert_eq!(
-        parse_immediate_6(CompleteStr("-25")),
-        Ok((CompleteStr(""), Immediate6(-25_i8)))
+    assert!(
+        parse_immediate_6(CompleteStr("-25")).is_err()
     );
 }
 
@@ -1077,17 +3488,15 @@ fn test_negitive_immediate9_parse() {
 
 #[test]
 fn test_negitive_immediate12_parse() {
-    assert_eq!(
-        parse_immediate_12(CompleteStr("-25")),
-        Ok((CompleteStr(""), Immediate12(-25_i16)))
+    assert!(
+        parse_immediate_12(CompleteStr("-25")).is_err()
     );
 }
 
 #[test]
 fn test_negitive_immediate16_parse() {
-    assert_eq!(
-        parse_immediate_16(CompleteStr("-25")),
-        Ok((CompleteStr(""), Immediate16(-25_i16)))
+    assert!(
+        parse_immediate_16(CompleteStr("-25")).is_err()
     );
 }
 
@@ -1107,6 +3516,186 @@ fn test_negitive_immediate26_parse() {
     );
 }
 
+#[test]
+fn test_hex_number_parse() {
+    assert_eq!(parse_number(CompleteStr("0xBEEF")), Ok((CompleteStr(""), 0xBEEF)));
+    assert_eq!(parse_number(CompleteStr("0Xff")), Ok((CompleteStr(""), 0xFF)));
+}
+
+#[test]
+fn test_binary_number_parse() {
+    assert_eq!(parse_number(CompleteStr("0b1010")), Ok((CompleteStr(""), 10)));
+}
+
+#[test]
+fn test_octal_number_parse() {
+    assert_eq!(parse_number(CompleteStr("0o17")), Ok((CompleteStr(""), 15)));
+}
+
+#[test]
+fn test_separated_number_parse() {
+    assert_eq!(parse_number(CompleteStr("1_000_000")), Ok((CompleteStr(""), 1_000_000)));
+    assert_eq!(parse_number(CompleteStr("0xFFFF_0000")), Ok((CompleteStr(""), 0xFFFF_0000)));
+    assert_eq!(parse_number(CompleteStr("-0b1000_0000")), Ok((CompleteStr(""), -128)));
+}
+
+#[test]
+fn test_leading_separator_number_parse() {
+    assert!(parse_line(CompleteStr("ANDI X1, X1, 0x_FF")).is_err());
+}
+
+#[test]
+fn test_character_number_parse() {
+    assert_eq!(parse_number(CompleteStr("'A'")), Ok((CompleteStr(""), 65)));
+    assert_eq!(parse_number(CompleteStr("'\\n'")), Ok((CompleteStr(""), 10)));
+    assert_eq!(parse_number(CompleteStr("'\\''")), Ok((CompleteStr(""), 39)));
+    assert_eq!(parse_number(CompleteStr("';'")), Ok((CompleteStr(""), 59)));
+}
+
+#[test]
+fn test_and_immediate_hex_parse() {
+    assert_eq!(
+        parse_instruction(CompleteStr("ANDI X1, X1, 0xFF")),
+        Ok((
+            CompleteStr(""),
+            Instruction::AndImmediate { n: Register::X1, m: Immediate12(0xFF), destination: Register::X1 }
+        ))
+    );
+}
+
+#[test]
+fn test_move_zero_hex_parse() {
+    assert_eq!(
+        parse_instruction(CompleteStr("MOVZ X2, 0xBEEF, LSL 0x10")),
+        Ok((
+            CompleteStr(""),
+            Instruction::MoveZero { immediate: Immediate16(0xBEEF_u16 as i16), shift: Shift16::Shift16, destination: Register::X2 }
+        ))
+    );
+}
+
+#[test]
+fn test_line_character_immediate_parse() {
+    assert_eq!(
+        parse_line(CompleteStr("SUBI X1, X1, ';' ; make it a number")),
+        Ok((
+            CompleteStr(""),
+            AsmLine::Commented(
+                Box::new(AsmLine::Instruction(Instruction::SubtractImmediate { n: Register::X1, m: Immediate12(59), destination: Register::X1 })),
+                "make it a number"
+            )
+        ))
+    );
+}
+
+#[test]
+fn test_lines_hex_out_of_range_parse() {
+    assert_eq!(
+        parse_lines("ORRI X1, X1, 0x1_000 ; '@'"),
+        vec![AsmLine::Error(Diagnostic::error(1, 14, "0x1_000", "immediate `0x1_000` is out of range for `ORRI`, expected 0 to 4095".to_owned()))]
+    );
+}
+
+#[test]
+fn test_hash_number_parse() {
+    assert_eq!(parse_number(CompleteStr("#-0x10")), Ok((CompleteStr(""), -16)));
+}
+
+#[test]
+fn test_display_round_trip() {
+    let lines = [
+        "ADD X1, X2, X3",
+        "SUB X4, X5, XZR",
+        "ADDI X1, X2, 4095",
+        "SUBI X28, X28, 16",
+        "ADDS X1, X2, X3",
+        "SUBS XZR, X1, X2",
+        "ADDIS X1, X2, 0",
+        "SUBIS XZR, X7, 50",
+        "STUR X23, [X7, #50]",
+        "LDUR X1, [X2, #-256]",
+        "STURB X3, [X4, #255]",
+        "LDURB X5, [X6, #0]",
+        "STURW X1, [X2, #-4]",
+        "LDURSW X3, [X4, #4]",
+        "STURH X5, [X6, #2]",
+        "LDURH X7, [X8, #-2]",
+        "LDXR X1, [X2, #0]",
+        "STXR X9, X10, [X11, #0]",
+        "ADD X1, X2, X3, LSL 4",
+        "SUB X1, X2, X3, LSL 1",
+        "AND X1, X2, X3, LSL 63",
+        "ORR X1, X2, X3, LSL 8",
+        "EOR X1, X2, X3, LSL 2",
+        "FADDS S1, S2, S3",
+        "FADDD D1, D2, D3",
+        "FSUBS S4, S5, S6",
+        "FSUBD D4, D5, D6",
+        "FMULS S7, S8, S9",
+        "FMULD D7, D8, D9",
+        "FDIVS S10, S11, S12",
+        "FDIVD D10, D11, D31",
+        "FCMPS S0, S1",
+        "FCMPD D0, D1",
+        "LDURS S1, [X2, #4]",
+        "STURS S1, [X2, #-4]",
+        "LDURD D3, [X28, #8]",
+        "STURD D3, [X28, #-8]",
+        "MOVZ X1, 4660, LSL 16",
+        "MOVK X2, 65535, LSL 0",
+        "AND X1, X2, X3",
+        "ORR X1, XZR, X3",
+        "EOR X30, X29, X28",
+        "ANDI X1, X2, 255",
+        "ORRI X1, X2, 4",
+        "EORI X1, X2, 1",
+        "ANDS X1, X2, X3",
+        "ANDIS X1, X2, 15",
+        "LSR X1, X2, 63",
+        "LSL X1, X2, 1",
+        "CBZ X1, -3",
+        "CBNZ X9, 262143",
+        "B.EQ 12",
+        "B.NV -262144",
+        "B -33554432",
+        "BR X30",
+        "BL 33554431",
+        "MUL X1, X2, X3",
+        "SMULH X4, X5, X6",
+        "UMULH X7, X8, X9",
+        "SDIV X10, X11, X12",
+        "UDIV X13, X14, XZR",
+    ];
+
+    for line in lines.iter() {
+        let (_, instruction) = parse_instruction(CompleteStr(line)).unwrap();
+        assert_eq!(&instruction.to_string(), line);
+        assert_eq!(parse_instruction(CompleteStr(&instruction.to_string())), Ok((CompleteStr(""), instruction)));
+    }
+}
+
+#[test]
+fn test_too_large_immediate12_parse() {
+    assert!(
+        parse_immediate_12(CompleteStr("5000")).is_err()
+    );
+}
+
+#[test]
+fn test_too_large_immediate6_parse() {
+    assert!(
+        parse_immediate_6(CompleteStr("64")).is_err()
+    );
+}
+
+#[test]
+fn test_largest_immediate16_parse() {
+    assert_eq!(
+        parse_immediate_16(CompleteStr("65535")),
+        Ok((CompleteStr(""), Immediate16(-1_i16)))
+    );
+}
+
 #[test]
 fn test_register_x1_parse() {
     assert_eq!(parse_register(CompleteStr("X0")), Ok((CompleteStr(""), Register::X0)));
@@ -1235,3 +3824,16 @@ fn test_register_x31_parse() {
 fn test_register_xzr_parse() {
     assert_eq!(parse_register(CompleteStr("XZR")), Ok((CompleteStr(""), Register::XZR)));
 }
+#[test]
+fn test_register_lower_case_parse() {
+    assert_eq!(parse_register(CompleteStr("x12")), Ok((CompleteStr(""), Register::X12)));
+    assert_eq!(parse_register(CompleteStr("xzr")), Ok((CompleteStr(""), Register::XZR)));
+}
+#[test]
+fn test_register_alias_parse() {
+    assert_eq!(parse_register(CompleteStr("IP0")), Ok((CompleteStr(""), Register::X16)));
+    assert_eq!(parse_register(CompleteStr("IP1")), Ok((CompleteStr(""), Register::X17)));
+    assert_eq!(parse_register(CompleteStr("SP")), Ok((CompleteStr(""), Register::X28)));
+    assert_eq!(parse_register(CompleteStr("fp")), Ok((CompleteStr(""), Register::X29)));
+    assert_eq!(parse_register(CompleteStr("Lr")), Ok((CompleteStr(""), Register::X30)));
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+
 use bit::Bit;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -36,20 +38,161 @@ pub enum Register {
     XZR,
 }
 
+impl Register {
+    /// Get the register with the given number
+    pub fn from_u8(number: u8) -> Option<Register> {
+        match number {
+            0 => Some(Register::X0),
+            1 => Some(Register::X1),
+            2 => Some(Register::X2),
+            3 => Some(Register::X3),
+            4 => Some(Register::X4),
+            5 => Some(Register::X5),
+            6 => Some(Register::X6),
+            7 => Some(Register::X7),
+            8 => Some(Register::X8),
+            9 => Some(Register::X9),
+            10 => Some(Register::X10),
+            11 => Some(Register::X11),
+            12 => Some(Register::X12),
+            13 => Some(Register::X13),
+            14 => Some(Register::X14),
+            15 => Some(Register::X15),
+            16 => Some(Register::X16),
+            17 => Some(Register::X17),
+            18 => Some(Register::X18),
+            19 => Some(Register::X19),
+            20 => Some(Register::X20),
+            21 => Some(Register::X21),
+            22 => Some(Register::X22),
+            23 => Some(Register::X23),
+            24 => Some(Register::X24),
+            25 => Some(Register::X25),
+            26 => Some(Register::X26),
+            27 => Some(Register::X27),
+            28 => Some(Register::X28),
+            29 => Some(Register::X29),
+            30 => Some(Register::X30),
+            31 => Some(Register::XZR),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Register::XZR => write!(f, "XZR"),
+            register => write!(f, "X{}", register as u8),
+        }
+    }
+}
+
+impl From<Register> for u32 {
+    fn from(register: Register) -> u32 {
+        register as u32
+    }
+}
+
 impl From<Register> for [Bit; 5] {
     fn from(register: Register) -> [Bit; 5] {
         let register_number = register as usize as u8;
 
         let mut result = [Bit::Zero; 5];
 
-        for i in 0..5 {
-            result[i] = Bit::from_u8((register_number >> i as u8) & 1_u8).unwrap();
+        for (i, bit) in result.iter_mut().enumerate() {
+            *bit = Bit::from_u8((register_number >> i as u8) & 1_u8).unwrap();
         }
 
         result
     }
 }
 
+/// One of the 32 floating point registers, named `S0` to `S31` when an
+/// instruction uses it for single precision and `D0` to `D31` for double
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FloatRegister {
+    F0 = 0,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    F25,
+    F26,
+    F27,
+    F28,
+    F29,
+    F30,
+    F31,
+}
+
+impl FloatRegister {
+    /// Get the register with the given number
+    pub fn from_u8(number: u8) -> Option<FloatRegister> {
+        match number {
+            0 => Some(FloatRegister::F0),
+            1 => Some(FloatRegister::F1),
+            2 => Some(FloatRegister::F2),
+            3 => Some(FloatRegister::F3),
+            4 => Some(FloatRegister::F4),
+            5 => Some(FloatRegister::F5),
+            6 => Some(FloatRegister::F6),
+            7 => Some(FloatRegister::F7),
+            8 => Some(FloatRegister::F8),
+            9 => Some(FloatRegister::F9),
+            10 => Some(FloatRegister::F10),
+            11 => Some(FloatRegister::F11),
+            12 => Some(FloatRegister::F12),
+            13 => Some(FloatRegister::F13),
+            14 => Some(FloatRegister::F14),
+            15 => Some(FloatRegister::F15),
+            16 => Some(FloatRegister::F16),
+            17 => Some(FloatRegister::F17),
+            18 => Some(FloatRegister::F18),
+            19 => Some(FloatRegister::F19),
+            20 => Some(FloatRegister::F20),
+            21 => Some(FloatRegister::F21),
+            22 => Some(FloatRegister::F22),
+            23 => Some(FloatRegister::F23),
+            24 => Some(FloatRegister::F24),
+            25 => Some(FloatRegister::F25),
+            26 => Some(FloatRegister::F26),
+            27 => Some(FloatRegister::F27),
+            28 => Some(FloatRegister::F28),
+            29 => Some(FloatRegister::F29),
+            30 => Some(FloatRegister::F30),
+            31 => Some(FloatRegister::F31),
+            _ => None,
+        }
+    }
+}
+
+impl From<FloatRegister> for u32 {
+    fn from(register: FloatRegister) -> u32 {
+        register as u32
+    }
+}
+
 #[test]
 fn test_resgister_bits_zero() {
     assert_eq!(<[Bit; 5]>::from(Register::X0), [Bit::Zero, Bit::Zero, Bit::Zero, Bit::Zero, Bit::Zero]);
@@ -65,3 +208,23 @@ fn test_resgister_bits_xrz() {
     assert_eq!(<[Bit; 5]>::from(Register::XZR), [Bit::One, Bit::One, Bit::One, Bit::One, Bit::One]);
 }
 
+#[test]
+fn test_register_from_u8() {
+    assert_eq!(Register::from_u8(7), Some(Register::X7));
+    assert_eq!(Register::from_u8(31), Some(Register::XZR));
+    assert_eq!(Register::from_u8(32), None);
+}
+
+#[test]
+fn test_register_display() {
+    assert_eq!(Register::X23.to_string(), "X23");
+    assert_eq!(Register::XZR.to_string(), "XZR");
+}
+
+#[test]
+fn test_float_register_from_u8() {
+    assert_eq!(FloatRegister::from_u8(0), Some(FloatRegister::F0));
+    assert_eq!(FloatRegister::from_u8(31), Some(FloatRegister::F31));
+    assert_eq!(FloatRegister::from_u8(32), None);
+    assert_eq!(u32::from(FloatRegister::F17), 17);
+}
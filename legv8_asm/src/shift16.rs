@@ -1,6 +1,8 @@
+use std::fmt;
+
 use bit::Bit;
 
-#[derive(Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Shift16 {
     Shift0 = 0,
     Shift16,
@@ -8,14 +10,40 @@ pub enum Shift16 {
     Shift48,
 }
 
+impl Shift16 {
+    /// Get the shift with the given two bit number
+    pub fn from_u8(number: u8) -> Option<Shift16> {
+        match number {
+            0 => Some(Shift16::Shift0),
+            1 => Some(Shift16::Shift16),
+            2 => Some(Shift16::Shift32),
+            3 => Some(Shift16::Shift48),
+            _ => None,
+        }
+    }
+}
+
+/// Format the shift as `LSL 16`
+impl fmt::Display for Shift16 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LSL {}", *self as u8 * 16)
+    }
+}
+
+impl From<Shift16> for u32 {
+    fn from(shift: Shift16) -> u32 {
+        shift as u32
+    }
+}
+
 impl From<Shift16> for [Bit; 2] {
     fn from(shift: Shift16) -> [Bit; 2] {
         let shift_number = shift as usize as u8;
 
         let mut result = [Bit::Zero; 2];
 
-        for i in 0..2 {
-            result[i] = Bit::from_u8((shift_number >> i as u8) & 1_u8).unwrap();
+        for (i, bit) in result.iter_mut().enumerate() {
+            *bit = Bit::from_u8((shift_number >> i as u8) & 1_u8).unwrap();
         }
 
         result
@@ -32,3 +60,13 @@ fn test_shift32() {
     assert_eq!(<[Bit; 2]>::from(Shift16::Shift32), [Bit::Zero, Bit::One]);
 }
 
+#[test]
+fn test_shift_from_u8() {
+    assert_eq!(Shift16::from_u8(2), Some(Shift16::Shift32));
+    assert_eq!(Shift16::from_u8(4), None);
+}
+
+#[test]
+fn test_shift_display() {
+    assert_eq!(Shift16::Shift48.to_string(), "LSL 48");
+}
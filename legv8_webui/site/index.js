@@ -4,7 +4,7 @@ var output_div = document.getElementById("output_div");
 var input = CodeMirror(input_div, {
     value: "ADD X3, X1, X2",
     lineNumbers: true,
-    firstLineNumber: 0,
+    firstLineNumber: 1,
 });
 
 input.setSize(window.innerWidth/2 - 10, window.innerHeight - 120);
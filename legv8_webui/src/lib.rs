@@ -1,3 +1,6 @@
+// The exported functions take pointers handed to us by the javascript side
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
 extern crate legv8_asm;
 
 use std::mem;
@@ -11,7 +14,7 @@ pub extern "C" fn alloc(size: usize) -> *mut c_void {
     let mut buf = Vec::with_capacity(size);
     let ptr = buf.as_mut_ptr();
     mem::forget(buf);
-    return ptr as *mut c_void;
+    ptr as *mut c_void
 }
 
 #[no_mangle]